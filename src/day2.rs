@@ -1,6 +1,12 @@
 use crate::prelude::*;
 use std::cmp::Ordering;
 
+pub mod rules;
+pub mod analysis;
+pub mod tournament;
+pub mod counter;
+pub mod laws;
+
 // Given a game of rock-paper-scissors given as "{A,B,C} {X,Y,Z}" (e.g. "A Z"), calculate
 // the score of each game and return the total score over all games.
 pub fn calculate_part1() -> Result<usize>{
@@ -21,8 +27,9 @@ pub fn calculate_part1() -> Result<usize>{
     Ok(total_score)
 }
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug)]
-enum RPS {
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum RPS {
     Rock = 1,
     Paper = 2,
     Scissors = 3,
@@ -40,8 +47,8 @@ impl TryFrom<&str> for RPS {
 }
 
 /// A non-transitive total order. 
-/// Reflexive, antisymmetric, strongly-connected.
-trait NonTransitiveOrder {
+/// Reflexive, antisymmetric, strongly-connected. Checked by `laws::check_laws`.
+pub trait NonTransitiveOrder {
     fn cmp(&self, other: &Self) -> Ordering;
 }
 impl NonTransitiveOrder for RPS {
@@ -69,6 +76,15 @@ fn score_game(player_pick: RPS, opp_pick: RPS) -> usize {
     score
 }
 
+/// Determine the result of a game from the player's point of view.
+fn game_result(player_pick: RPS, opp_pick: RPS) -> GameResult {
+    match player_pick.cmp(&opp_pick) {
+        Ordering::Less => Loss,
+        Ordering::Equal => Draw,
+        Ordering::Greater => Win,
+    }
+}
+
 /********** Part 2 begins **********/
 
 // Turns out the second column is the required game result, not your move.
@@ -91,8 +107,8 @@ pub fn calculate_part2() -> Result<usize>{
     Ok(total_score)
 }
 
-#[derive(PartialEq, Eq, Copy, Clone)]
-enum GameResult {
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum GameResult {
     Win,
     Draw,
     Loss,
@@ -0,0 +1,173 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{RPS, RPS::*, GameResult, GameResult::*, score_game, game_result, get_symbol_for_outcome};
+
+/**/
+// The second column of the guide is ambiguous: X/Y/Z could be any permutation of moves, or the required outcome.
+// Score the guide under every one of these interpretations and compare them.
+/**/
+
+/// One way of reading the second column of the guide.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Interpretation {
+    /// X, Y and Z are the given moves, in that order.
+    Moves([RPS; 3]),
+    /// X, Y and Z are loss, draw and win.
+    Outcomes,
+}
+impl Interpretation {
+    /// All 6 move permutations, followed by the outcome interpretation.
+    fn all() -> Vec<Interpretation> {
+        let mut interpretations: Vec<Interpretation> = [
+            [Rock, Paper, Scissors],
+            [Rock, Scissors, Paper],
+            [Paper, Rock, Scissors],
+            [Paper, Scissors, Rock],
+            [Scissors, Rock, Paper],
+            [Scissors, Paper, Rock],
+        ].into_iter().map(Interpretation::Moves).collect();
+        interpretations.push(Interpretation::Outcomes);
+        interpretations
+    }
+
+    /// Given the opponent's move and the second column, determine what move we play.
+    fn player_pick(&self, opp_pick: RPS, symbol: &str) -> Result<RPS> {
+        let index = match symbol {
+            "X" => 0,
+            "Y" => 1,
+            "Z" => 2,
+            _ => return Err(anyhow!("Invalid char!")),
+        };
+        Ok(match self {
+            Interpretation::Moves(moves) => moves[index],
+            Interpretation::Outcomes => get_symbol_for_outcome(opp_pick, [Loss, Draw, Win][index]),
+        })
+    }
+}
+impl fmt::Display for Interpretation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Interpretation::Moves([x, y, z]) => write!(f, "X={x:?} Y={y:?} Z={z:?}"),
+            Interpretation::Outcomes => write!(f, "X=Loss Y=Draw Z=Win"),
+        }
+    }
+}
+
+/// A single scored round of the guide.
+#[derive(Clone, Debug)]
+struct Round {
+    line_no: usize,
+    opp_pick: RPS,
+    player_pick: RPS,
+    result: GameResult,
+    score: usize,
+}
+
+/// Totals for the whole guide under one interpretation.
+#[derive(Clone, Debug)]
+struct Summary {
+    interpretation: Interpretation,
+    total_score: usize,
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+/// Every interpretation of the guide ranked by total score, with the per-round breakdown of the best.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    ranking: Vec<Summary>,
+    top_rounds: Vec<Round>,
+}
+
+pub fn analyse(path: &str) -> Result<Analysis> {
+    // Parse the guide once as (line number, opponent's move, second column).
+    let contents = std::fs::read_to_string(path)?;
+    let mut guide: Vec<(usize, RPS, &str)> = vec![];
+    for (n, line) in contents.lines().enumerate() {
+        if line.is_empty() {continue}
+        let game: Vec<&str> = line.split(' ').collect();
+        if game.len() != 2 {
+            return Err(anyhow!("Line {}: expected two columns, found '{line}'", n + 1));
+        }
+        guide.push((n + 1, game[0].try_into()?, game[1]));
+    }
+
+    let mut scored: Vec<(Summary, Vec<Round>)> = vec![];
+    for interpretation in Interpretation::all() {
+        let rounds = guide.iter()
+            .map(|&(line_no, opp_pick, symbol)| {
+                let player_pick = interpretation.player_pick(opp_pick, symbol)?;
+                Ok(Round {
+                    line_no,
+                    opp_pick,
+                    player_pick,
+                    result: game_result(player_pick, opp_pick),
+                    score: score_game(player_pick, opp_pick),
+                })
+            })
+            .collect::<Result<Vec<Round>>>()?;
+
+        let count = |result: GameResult| rounds.iter().filter(|r| r.result == result).count();
+        let summary = Summary {
+            interpretation,
+            total_score: rounds.iter().map(|r| r.score).sum(),
+            wins: count(Win),
+            draws: count(Draw),
+            losses: count(Loss),
+        };
+        scored.push((summary, rounds));
+    }
+
+    // Stable sort, so ties keep the order given by Interpretation::all().
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(s.total_score));
+    let top_rounds = scored[0].1.clone();
+    let ranking = scored.into_iter().map(|(summary, _)| summary).collect();
+    Ok(Analysis { ranking, top_rounds })
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Rank  Interpretation                     Score  Wins  Draws  Losses")?;
+        for (rank, s) in self.ranking.iter().enumerate() {
+            writeln!(f, "{:>4}  {:<33} {:>6} {:>5} {:>6} {:>7}",
+                rank + 1, s.interpretation.to_string(), s.total_score, s.wins, s.draws, s.losses)?;
+        }
+
+        // Ties go to the higher-ranked interpretation.
+        let most_wins = self.ranking.iter()
+            .reduce(|best, s| if s.wins > best.wins {s} else {best})
+            .expect("There is always at least one interpretation");
+        let rounds = most_wins.wins + most_wins.draws + most_wins.losses;
+        writeln!(f)?;
+        writeln!(f, "Most rounds won: {} ({} of {rounds} rounds, {} drawn, {} lost)",
+            most_wins.interpretation, most_wins.wins, most_wins.draws, most_wins.losses)?;
+
+        writeln!(f)?;
+        writeln!(f, "Rounds under {}:", self.ranking[0].interpretation)?;
+        for round in &self.top_rounds {
+            writeln!(f, "  line {:>4}: {:?} vs {:?} -> {:?}, {} points",
+                round.line_no, round.player_pick, round.opp_pick, round.result, round.score)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_both_parts_and_ranks_by_score() {
+        let analysis = analyse("input/day2.txt").unwrap();
+        let score = |interpretation| analysis.ranking.iter()
+            .find(|s| s.interpretation == interpretation).unwrap().total_score;
+        assert_eq!(score(Interpretation::Moves([Rock, Paper, Scissors])), 9241);
+        assert_eq!(score(Interpretation::Outcomes), 14610);
+
+        let scores: Vec<usize> = analysis.ranking.iter().map(|s| s.total_score).collect();
+        assert_eq!(scores, [15777, 14610, 13603, 13074, 12482, 10823, 9241]);
+        assert_eq!(analysis.ranking[0].interpretation, Interpretation::Moves([Scissors, Rock, Paper]));
+        assert_eq!(analysis.top_rounds.iter().map(|r| r.score).sum::<usize>(), analysis.ranking[0].total_score);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{RPS, RPS::*, GameResult::*, score_game, game_result};
+use super::rules::{RuleSet, calculate_with_rules};
+
+/**/
+// Work out how well we could have done against the opponent's moves in a strategy guide.
+// The best response is found with dynamic programming over (round, wins so far, last move, current run length),
+// and the mixed-strategy Nash equilibrium of the scoring matrix by support enumeration.
+/**/
+
+const MOVES: [RPS; 3] = [Rock, Paper, Scissors];
+
+/// Limits on the sequence of moves we're allowed to play. `None` means unconstrained.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Constraints {
+    /// Win at most this many rounds.
+    pub max_wins: Option<usize>,
+    /// Never play the same move more than this many times in a row.
+    pub max_run: Option<usize>,
+}
+
+/// Find the sequence of moves that scores the most against `opp_picks` while respecting the constraints.
+// The state space is rounds * (max_wins + 1) * 3 * max_run, so very large limits on both are slow.
+pub fn best_response(opp_picks: &[RPS], constraints: Constraints) -> Result<Vec<RPS>> {
+    let n = opp_picks.len();
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    // Unconstrained dimensions collapse to a single value, and limits above the number of rounds are pointless.
+    let wins_dim = constraints.max_wins.map_or(1, |k| k.min(n) + 1);
+    let run_dim = constraints.max_run.map_or(1, |m| m.min(n));
+    if run_dim == 0 {
+        return Err(anyhow!("A maximum run of 0 means no moves can be played!"));
+    }
+    let states = wins_dim * 3 * run_dim;
+    let index = |wins: usize, last: usize, run: usize| (wins * 3 + last) * run_dim + run - 1;
+    let decode = |state: usize| (state / (3 * run_dim), (state / run_dim) % 3, state % run_dim + 1);
+
+    // Where we'd end up after playing `mv` from (wins, last, run) against `opp_pick`, if that's allowed.
+    let step = |wins: usize, last: Option<usize>, run: usize, mv: usize, opp_pick: RPS| -> Option<usize> {
+        let won = game_result(MOVES[mv], opp_pick) == Win;
+        let wins = if constraints.max_wins.is_some() { wins + won as usize } else { 0 };
+        let run = if constraints.max_run.is_some() && last == Some(mv) { run + 1 } else { 1 };
+        (wins < wins_dim && run <= run_dim).then(|| index(wins, mv, run))
+    };
+
+    // best[state] is the highest score of any sequence so far ending in that state.
+    let mut best: Vec<Option<usize>> = vec![None; states];
+    for (mv, &pick) in MOVES.iter().enumerate() {
+        if let Some(state) = step(0, None, 0, mv, opp_picks[0]) {
+            best[state] = Some(score_game(pick, opp_picks[0]));
+        }
+    }
+
+    // parents[i][state] is the state we came from to reach `state` in round i + 1.
+    let mut parents: Vec<Vec<u32>> = Vec::with_capacity(n - 1);
+    for &opp_pick in &opp_picks[1..] {
+        let mut next: Vec<Option<usize>> = vec![None; states];
+        let mut parent = vec![u32::MAX; states];
+        for (state, score) in best.iter().enumerate() {
+            let Some(score) = score else {continue};
+            let (wins, last, run) = decode(state);
+            for (mv, &pick) in MOVES.iter().enumerate() {
+                let Some(next_state) = step(wins, Some(last), run, mv, opp_pick) else {continue};
+                let candidate = score + score_game(pick, opp_pick);
+                if next[next_state].is_none_or(|s| s < candidate) {
+                    next[next_state] = Some(candidate);
+                    parent[next_state] = state as u32;
+                }
+            }
+        }
+        parents.push(parent);
+        best = next;
+    }
+
+    // Walk back from the best final state.
+    let (mut state, _) = best.iter().enumerate()
+        .filter_map(|(state, score)| score.map(|s| (state, s)))
+        .max_by_key(|&(_, score)| score)
+        .ok_or(anyhow!("No sequence of moves satisfies the constraints!"))?;
+    let mut picks = vec![MOVES[decode(state).1]];
+    for parent in parents.iter().rev() {
+        state = parent[state] as usize;
+        picks.push(MOVES[decode(state).1]);
+    }
+    picks.reverse();
+    Ok(picks)
+}
+
+/// score_matrix()[i][j] is the score for playing MOVES[i] against MOVES[j].
+fn score_matrix() -> [[f64; 3]; 3] {
+    let mut matrix = [[0.0; 3]; 3];
+    for (i, &player_pick) in MOVES.iter().enumerate() {
+        for (j, &opp_pick) in MOVES.iter().enumerate() {
+            matrix[i][j] = score_game(player_pick, opp_pick) as f64;
+        }
+    }
+    matrix
+}
+
+/// Solve a square linear system by Gaussian elimination with partial pivoting. Returns None if singular.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&r1, &r2| a[r1][col].abs().total_cmp(&a[r2][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col+1..n {
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col].clone();
+            for (value, pivot_value) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row+1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+/// The symmetric mixed-strategy Nash equilibrium of the game scored by `score_game`.
+/// Returns the probability of playing rock, paper and scissors, and the expected score per round.
+pub fn nash_equilibrium() -> ([f64; 3], f64) {
+    const EPSILON: f64 = 1e-9;
+    let matrix = score_matrix();
+
+    // Try every support, largest first. On the support every move must score the same (v) against the mix,
+    // and no move outside it may score more.
+    for support in (1..8u8).rev() {
+        let members: Vec<usize> = (0..3).filter(|i| support & (1 << i) != 0).collect();
+        let size = members.len();
+
+        // Unknowns are the probabilities of each member followed by v.
+        let mut a = vec![vec![0.0; size + 1]; size + 1];
+        let mut b = vec![0.0; size + 1];
+        for (row, &i) in members.iter().enumerate() {
+            for (col, &j) in members.iter().enumerate() {
+                a[row][col] = matrix[i][j];
+            }
+            a[row][size] = -1.0;
+        }
+        a[size][..size].fill(1.0);
+        b[size] = 1.0;
+
+        let Some(x) = solve(a, b) else {continue};
+        if x[..size].iter().any(|&p| p < -EPSILON) {continue}
+
+        let mut strategy = [0.0; 3];
+        for (k, &i) in members.iter().enumerate() {
+            strategy[i] = x[k].max(0.0);
+        }
+        let value = x[size];
+        let payoff = |i: usize| (0..3).map(|j| matrix[i][j] * strategy[j]).sum::<f64>();
+        if (0..3).all(|i| payoff(i) <= value + EPSILON) {
+            return (strategy, value);
+        }
+    }
+    unreachable!("Every finite symmetric game has a symmetric equilibrium");
+}
+
+/// How a guide compares to the best possible responses to the opponent's moves.
+#[derive(Clone, Debug)]
+pub struct CounterReport {
+    rounds: usize,
+    part1_score: usize,
+    part2_score: usize,
+    constraints: Constraints,
+    optimal: Vec<RPS>,
+    optimal_score: usize,
+    optimal_wins: usize,
+    /// The best score with no constraints at all, which the guide and the Nash mix are compared against.
+    unconstrained_score: usize,
+    nash: [f64; 3],
+    nash_value: f64,
+    /// Expected score of playing the Nash mix against the opponent's actual moves.
+    nash_score: f64,
+}
+
+pub fn compare_to_optimal(path: &str, constraints: Constraints) -> Result<CounterReport> {
+    let contents = std::fs::read_to_string(path)?;
+    let opp_picks = contents.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split(' ').next().unwrap().try_into())
+        .collect::<Result<Vec<RPS>>>()?;
+
+    let optimal = best_response(&opp_picks, constraints)?;
+    let optimal_score = optimal.iter().zip(&opp_picks).map(|(&mine, &opp)| score_game(mine, opp)).sum();
+    let optimal_wins = optimal.iter().zip(&opp_picks).filter(|(&mine, &opp)| game_result(mine, opp) == Win).count();
+    // With no constraints, the best response is just the best move in every round.
+    let unconstrained_score = opp_picks.iter()
+        .map(|&opp| MOVES.iter().map(|&mine| score_game(mine, opp)).max().unwrap())
+        .sum();
+
+    let (nash, nash_value) = nash_equilibrium();
+    let matrix = score_matrix();
+    let nash_score = opp_picks.iter()
+        .map(|&opp| {
+            let j = MOVES.iter().position(|&m| m == opp).unwrap();
+            (0..3).map(|i| nash[i] * matrix[i][j]).sum::<f64>()
+        })
+        .sum();
+
+    Ok(CounterReport {
+        rounds: opp_picks.len(),
+        part1_score: calculate_with_rules(path, &RuleSet::part1())?,
+        part2_score: calculate_with_rules(path, &RuleSet::part2())?,
+        constraints,
+        optimal,
+        optimal_score,
+        optimal_wins,
+        unconstrained_score,
+        nash,
+        nash_value,
+        nash_score,
+    })
+}
+
+impl fmt::Display for CounterReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |score: f64| 100.0 * score / self.unconstrained_score.max(1) as f64;
+        let limit = |l: Option<usize>| l.map_or("none".to_string(), |l| l.to_string());
+
+        writeln!(f, "Rounds: {}", self.rounds)?;
+        writeln!(f, "Constraints: max wins {}, max run {}", limit(self.constraints.max_wins), limit(self.constraints.max_run))?;
+        writeln!(f, "Optimal score: {} ({} wins)", self.optimal_score, self.optimal_wins)?;
+        writeln!(f, "Unconstrained optimal score: {}", self.unconstrained_score)?;
+        writeln!(f, "Guide as part 1: {} ({:.1}% of unconstrained optimal)", self.part1_score, percent(self.part1_score as f64))?;
+        writeln!(f, "Guide as part 2: {} ({:.1}% of unconstrained optimal)", self.part2_score, percent(self.part2_score as f64))?;
+        writeln!(f, "Nash equilibrium: rock {:.4}, paper {:.4}, scissors {:.4} (value {:.4} per round)",
+            self.nash[0], self.nash[1], self.nash[2], self.nash_value)?;
+        writeln!(f, "Nash mix against these moves: {:.1} expected ({:.1}% of unconstrained optimal)",
+            self.nash_score, percent(self.nash_score))?;
+
+        const SHOWN: usize = 50;
+        let moves: String = self.optimal.iter().take(SHOWN)
+            .map(|m| match m { Rock => 'R', Paper => 'P', Scissors => 'S' })
+            .collect();
+        writeln!(f, "Optimal moves: {moves}{}", if self.optimal.len() > SHOWN {"..."} else {""})?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(picks: &[RPS], opp_picks: &[RPS]) -> usize {
+        picks.iter().zip(opp_picks).map(|(&mine, &opp)| score_game(mine, opp)).sum()
+    }
+
+    #[test]
+    fn best_response_respects_constraints() {
+        let opp_picks = [Rock; 3];
+        let unconstrained = best_response(&opp_picks, Constraints::default()).unwrap();
+        assert_eq!(unconstrained, [Paper; 3]);
+
+        // One win (8), and draws (4) beat losses (3) for the rest.
+        let one_win = best_response(&opp_picks, Constraints { max_wins: Some(1), max_run: None }).unwrap();
+        assert_eq!(score(&one_win, &opp_picks), 16);
+        assert_eq!(one_win.iter().filter(|&&pick| pick == Paper).count(), 1);
+
+        let no_repeats = best_response(&opp_picks, Constraints { max_wins: None, max_run: Some(1) }).unwrap();
+        assert_eq!(no_repeats, [Paper, Rock, Paper]);
+
+        assert!(best_response(&opp_picks, Constraints { max_wins: None, max_run: Some(0) }).is_err());
+    }
+
+    #[test]
+    fn finds_nash_equilibrium() {
+        let (strategy, value) = nash_equilibrium();
+        for (p, expected) in strategy.iter().zip([4.0 / 9.0, 1.0 / 9.0, 4.0 / 9.0]) {
+            assert!((p - expected).abs() < 1e-9);
+        }
+        assert!((value - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn compares_against_unconstrained_optimum() {
+        let constraints = Constraints { max_wins: Some(10), max_run: None };
+        let report = compare_to_optimal("input/day2.txt", constraints).unwrap();
+        assert_eq!((report.optimal_score, report.optimal_wins, report.unconstrained_score), (12032, 10, 19913));
+        // The guide beats the constrained optimum, but is still compared to the best possible score.
+        assert!(report.part2_score > report.optimal_score);
+        assert!(report.to_string().contains("14610 (73.4% of unconstrained optimal)"));
+    }
+}
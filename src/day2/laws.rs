@@ -0,0 +1,168 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use super::{NonTransitiveOrder, RPS::*};
+
+/**/
+// Exhaustively check that a `NonTransitiveOrder` implementation obeys the laws it claims, by enumerating
+// every pair and triple of values. Only works for types with a small, finite set of values.
+/**/
+
+/// A type with a finite, enumerable set of values.
+pub trait Finite: Sized {
+    fn all() -> Vec<Self>;
+}
+impl Finite for super::RPS {
+    fn all() -> Vec<Self> {
+        vec![Rock, Paper, Scissors]
+    }
+}
+
+/// A broken law, with the values that break it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Violation<T> {
+    /// a.cmp(a) isn't Equal.
+    NotReflexive(T),
+    /// a.cmp(b) isn't the reverse of b.cmp(a).
+    NotAntisymmetric(T, T),
+    /// a.cmp(b) is Equal for distinct a and b.
+    EqualButDistinct(T, T),
+}
+
+#[derive(Clone, Debug)]
+pub struct LawReport<T> {
+    pub violations: Vec<Violation<T>>,
+    /// The tournament graph: (a, b) means a beats b.
+    pub beats: Vec<(T, T)>,
+    /// Triples where a beats b and b beats c, but a doesn't beat c.
+    pub intransitive_triples: Vec<(T, T, T)>,
+    /// A cycle of three or more values in the tournament graph, e.g. [a, b, c] where a beats b, b beats c and c beats a.
+    pub cycle: Option<Vec<T>>,
+}
+impl<T> LawReport<T> {
+    /// Reflexive, antisymmetric and strongly-connected.
+    /// Strong connectivity (a <= b or b <= a) holds by construction, since `cmp` always returns an `Ordering`.
+    pub fn is_lawful(&self) -> bool {
+        self.violations.is_empty()
+    }
+    pub fn is_non_transitive(&self) -> bool {
+        self.cycle.is_some()
+    }
+}
+
+pub fn check_laws<T: NonTransitiveOrder + Finite + PartialEq + Clone + Debug>() -> LawReport<T> {
+    let values = T::all();
+    let mut violations = vec![];
+    let mut beats = vec![];
+
+    for a in &values {
+        if a.cmp(a) != Ordering::Equal {
+            violations.push(Violation::NotReflexive(a.clone()));
+        }
+    }
+
+    for (i, a) in values.iter().enumerate() {
+        for b in &values[i+1..] {
+            let (ab, ba) = (a.cmp(b), b.cmp(a));
+            if ab != ba.reverse() {
+                violations.push(Violation::NotAntisymmetric(a.clone(), b.clone()));
+            }
+            if ab == Ordering::Equal && a != b {
+                violations.push(Violation::EqualButDistinct(a.clone(), b.clone()));
+            }
+        }
+        for b in &values {
+            if a.cmp(b) == Ordering::Greater {
+                beats.push((a.clone(), b.clone()));
+            }
+        }
+    }
+
+    let mut intransitive_triples = vec![];
+    for a in &values {
+        for b in &values {
+            for c in &values {
+                let chain = a.cmp(b) == Ordering::Greater && b.cmp(c) == Ordering::Greater;
+                if chain && a.cmp(c) != Ordering::Greater {
+                    intransitive_triples.push((a.clone(), b.clone(), c.clone()));
+                }
+            }
+        }
+    }
+
+    let cycle = find_cycle(&values);
+    LawReport { violations, beats, intransitive_triples, cycle }
+}
+
+/// Depth-first search of the tournament graph, returning the first cycle of at least three values found.
+/// A value beating itself, or two values beating each other, are law violations rather than non-transitivity,
+/// so they don't count. Every simple path is tried, which is fine for the small types this works with.
+fn find_cycle<T: NonTransitiveOrder + Clone>(values: &[T]) -> Option<Vec<T>> {
+    // Only looks for cycles where `start` is the first value, so each cycle is only searched for once.
+    fn visit<T: NonTransitiveOrder>(node: usize, start: usize, values: &[T], on_path: &mut [bool], path: &mut Vec<usize>) -> Option<Vec<usize>> {
+        on_path[node] = true;
+        path.push(node);
+        for next in start..values.len() {
+            if next == node || values[node].cmp(&values[next]) != Ordering::Greater {continue}
+            if next == start {
+                if path.len() >= 3 {
+                    return Some(path.clone());
+                }
+            } else if !on_path[next] {
+                if let Some(cycle) = visit(next, start, values, on_path, path) {
+                    return Some(cycle);
+                }
+            }
+        }
+        path.pop();
+        on_path[node] = false;
+        None
+    }
+
+    let mut on_path = vec![false; values.len()];
+    (0..values.len())
+        .find_map(|start| visit(start, start, values, &mut on_path, &mut vec![]))
+        .map(|cycle| cycle.into_iter().map(|i| values[i].clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day2::RPS;
+
+    #[test]
+    fn rps_is_lawful_and_non_transitive() {
+        let report = check_laws::<RPS>();
+        assert!(report.is_lawful(), "{:?}", report.violations);
+        assert!(report.is_non_transitive());
+        assert_eq!(report.beats.len(), 3);
+        assert_eq!(report.intransitive_triples.len(), 3);
+        assert_eq!(report.cycle, Some(vec![Rock, Scissors, Paper]));
+    }
+
+    // A broken order: not reflexive for A, and A and B both claim to beat each other.
+    #[derive(PartialEq, Clone, Debug)]
+    enum Broken { A, B }
+    impl Finite for Broken {
+        fn all() -> Vec<Self> { vec![Broken::A, Broken::B] }
+    }
+    impl NonTransitiveOrder for Broken {
+        fn cmp(&self, other: &Self) -> Ordering {
+            match (self, other) {
+                (Broken::B, Broken::B) => Ordering::Equal,
+                _ => Ordering::Greater,
+            }
+        }
+    }
+
+    #[test]
+    fn broken_order_reports_violations() {
+        let report = check_laws::<Broken>();
+        assert_eq!(report.violations, vec![
+            Violation::NotReflexive(Broken::A),
+            Violation::NotAntisymmetric(Broken::A, Broken::B),
+        ]);
+        // A beating itself and A and B beating each other aren't cycles of three or more.
+        assert_eq!(report.cycle, None);
+        assert!(!report.is_non_transitive());
+    }
+}
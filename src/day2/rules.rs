@@ -0,0 +1,287 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+use super::{RPS, RPS::*, GameResult, GameResult::*, game_result};
+
+/**/
+// Configurable interpretations of the strategy guide.
+//
+// A rules file is a small INI/TOML-style document. Top-level `columns` lists what each column of the
+// guide means, and there is one section per column role mapping letters to moves or outcomes:
+//
+//     columns = opponent, player
+//     [opponent]
+//     A = rock
+//     ...
+//     [score]
+//     rock = 1
+//     ...
+//     win = 6
+//
+// Blank lines and lines starting with '#' are ignored.
+/**/
+
+const PART1_RULES: &str = "
+# Both columns are moves.
+columns = opponent, player
+
+[opponent]
+A = rock
+B = paper
+C = scissors
+
+[player]
+X = rock
+Y = paper
+Z = scissors
+
+[score]
+rock = 1
+paper = 2
+scissors = 3
+loss = 0
+draw = 3
+win = 6
+";
+
+const PART2_RULES: &str = "
+# The second column is the required outcome.
+columns = opponent, outcome
+
+[opponent]
+A = rock
+B = paper
+C = scissors
+
+[outcome]
+X = loss
+Y = draw
+Z = win
+
+[score]
+rock = 1
+paper = 2
+scissors = 3
+loss = 0
+draw = 3
+win = 6
+";
+
+/// What a column of the strategy guide encodes.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Column {
+    Opponent,
+    Player,
+    Outcome,
+}
+impl TryFrom<&str> for Column {
+    type Error = anyhow::Error;
+    fn try_from(str: &str) -> Result<Column> {
+        match str {
+            "opponent" => Ok(Column::Opponent),
+            "player" => Ok(Column::Player),
+            "outcome" => Ok(Column::Outcome),
+            _ => Err(anyhow!("Invalid column role '{str}'!"))
+        }
+    }
+}
+
+/// Column meanings, letter mappings and scoring for one interpretation of the strategy guide.
+#[derive(Clone, Debug)]
+pub struct RuleSet {
+    columns: Vec<Column>,
+    opponent: HashMap<String, RPS>,
+    player: HashMap<String, RPS>,
+    outcome: HashMap<String, GameResult>,
+    shape_scores: HashMap<RPS, usize>,
+    outcome_scores: HashMap<GameResult, usize>,
+}
+
+impl RuleSet {
+    /// The interpretation used by part 1: both columns are moves.
+    pub fn part1() -> RuleSet {
+        RuleSet::parse(PART1_RULES).expect("Built-in part 1 rules are invalid!")
+    }
+
+    /// The interpretation used by part 2: the second column is the required outcome.
+    pub fn part2() -> RuleSet {
+        RuleSet::parse(PART2_RULES).expect("Built-in part 2 rules are invalid!")
+    }
+
+    pub fn from_file(path: &str) -> Result<RuleSet> {
+        let contents = std::fs::read_to_string(path)?;
+        RuleSet::parse(&contents)
+    }
+
+    /// Parse a rules document. See the top of this file for the format.
+    pub fn parse(contents: &str) -> Result<RuleSet> {
+        let mut columns: Vec<Column> = vec![];
+        let mut opponent = HashMap::new();
+        let mut player = HashMap::new();
+        let mut outcome = HashMap::new();
+        let mut shape_scores = HashMap::new();
+        let mut outcome_scores = HashMap::new();
+        let mut section: Option<&str> = None;
+
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {continue}
+            let line_no = n + 1;
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(name.trim());
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .ok_or(anyhow!("Line {line_no}: expected 'key = value'."))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match section {
+                None if key == "columns" => {
+                    columns = value.split(',')
+                        .map(|role| Column::try_from(role.trim()))
+                        .collect::<Result<Vec<Column>>>()
+                        .map_err(|e| anyhow!("Line {line_no}: {e}"))?;
+                },
+                None => return Err(anyhow!("Line {line_no}: unknown top-level key '{key}'.")),
+                Some("opponent") => {opponent.insert(key.to_string(), parse_shape(value, line_no)?);},
+                Some("player") => {player.insert(key.to_string(), parse_shape(value, line_no)?);},
+                Some("outcome") => {outcome.insert(key.to_string(), parse_result(value, line_no)?);},
+                Some("score") => {
+                    let score: usize = value.parse()
+                        .map_err(|_| anyhow!("Line {line_no}: invalid score '{value}'."))?;
+                    match key {
+                        "rock" => shape_scores.insert(Rock, score),
+                        "paper" => shape_scores.insert(Paper, score),
+                        "scissors" => shape_scores.insert(Scissors, score),
+                        "loss" => outcome_scores.insert(Loss, score),
+                        "draw" => outcome_scores.insert(Draw, score),
+                        "win" => outcome_scores.insert(Win, score),
+                        _ => return Err(anyhow!("Line {line_no}: unknown score key '{key}'.")),
+                    };
+                },
+                Some(name) => return Err(anyhow!("Line {line_no}: unknown section '[{name}]'.")),
+            }
+        }
+
+        let rules = RuleSet { columns, opponent, player, outcome, shape_scores, outcome_scores };
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Check the rules describe exactly one opponent move and exactly one way of choosing ours, and that every
+    /// move and result has a score.
+    fn validate(&self) -> Result<()> {
+        let count = |role: Column| self.columns.iter().filter(|c| **c == role).count();
+        if count(Column::Opponent) != 1 {
+            return Err(anyhow!("Rules must have exactly one opponent column!"));
+        }
+        if count(Column::Player) + count(Column::Outcome) != 1 {
+            return Err(anyhow!("Rules must have exactly one player or outcome column!"));
+        }
+        for (role, mapping_empty) in [
+            (Column::Opponent, self.opponent.is_empty()),
+            (Column::Player, self.player.is_empty()),
+            (Column::Outcome, self.outcome.is_empty()),
+        ] {
+            if count(role) == 1 && mapping_empty {
+                return Err(anyhow!("No letter mapping given for the {role:?} column!"));
+            }
+        }
+        if self.shape_scores.len() != 3 || self.outcome_scores.len() != 3 {
+            return Err(anyhow!("Scores must be given for rock, paper, scissors, loss, draw and win!"));
+        }
+        Ok(())
+    }
+
+    /// Interpret one line of the guide, returning (player_pick, opp_pick).
+    pub(super) fn interpret(&self, line: &str) -> Result<(RPS, RPS)> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != self.columns.len() {
+            return Err(anyhow!("Expected {} columns, found {} in '{line}'", self.columns.len(), fields.len()));
+        }
+
+        let mut opp_pick = None;
+        let mut player_pick = None;
+        let mut required_outcome = None;
+        for (column, field) in self.columns.iter().zip(fields) {
+            let unmapped = || anyhow!("Unmapped symbol '{field}' in {column:?} column!");
+            match column {
+                Column::Opponent => opp_pick = Some(*self.opponent.get(field).ok_or_else(unmapped)?),
+                Column::Player => player_pick = Some(*self.player.get(field).ok_or_else(unmapped)?),
+                Column::Outcome => required_outcome = Some(*self.outcome.get(field).ok_or_else(unmapped)?),
+            }
+        }
+
+        // validate() guarantees these are present.
+        let opp_pick = opp_pick.unwrap();
+        let player_pick = match (player_pick, required_outcome) {
+            (Some(pick), _) => pick,
+            (None, Some(outcome)) => super::get_symbol_for_outcome(opp_pick, outcome),
+            (None, None) => unreachable!(),
+        };
+        Ok((player_pick, opp_pick))
+    }
+
+    /// Same as `score_game`, but using this rule set's scores.
+    pub(super) fn score(&self, player_pick: RPS, opp_pick: RPS) -> usize {
+        self.shape_scores[&player_pick] + self.outcome_scores[&game_result(player_pick, opp_pick)]
+    }
+}
+
+fn parse_shape(value: &str, line_no: usize) -> Result<RPS> {
+    match value {
+        "rock" => Ok(Rock),
+        "paper" => Ok(Paper),
+        "scissors" => Ok(Scissors),
+        _ => Err(anyhow!("Line {line_no}: invalid move '{value}'.")),
+    }
+}
+
+fn parse_result(value: &str, line_no: usize) -> Result<GameResult> {
+    match value {
+        "loss" => Ok(Loss),
+        "draw" => Ok(Draw),
+        "win" => Ok(Win),
+        _ => Err(anyhow!("Line {line_no}: invalid outcome '{value}'.")),
+    }
+}
+
+// Score a strategy guide under the given rules and return the total score over all games.
+pub fn calculate_with_rules(path: &str, rules: &RuleSet) -> Result<usize> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut total_score = 0usize;
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {continue}
+
+        let (my_pick, opponent_pick) = rules.interpret(&line)?;
+        total_score += rules.score(my_pick, opponent_pick);
+    }
+    Ok(total_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_rules_match_both_parts() {
+        assert_eq!(calculate_with_rules("input/day2.txt", &RuleSet::part1()).unwrap(), 9241);
+        assert_eq!(calculate_with_rules("input/day2.txt", &RuleSet::part2()).unwrap(), 14610);
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        let error = |rules: String| RuleSet::parse(&rules).unwrap_err().to_string();
+        assert_eq!(error(PART1_RULES.replace("[player]", "[players]")), "Line 11: unknown section '[players]'.");
+        assert_eq!(error(PART1_RULES.replace("win = 6", "")),
+            "Scores must be given for rock, paper, scissors, loss, draw and win!");
+        assert_eq!(error(PART1_RULES.replace("opponent, player", "player, player")),
+            "Rules must have exactly one opponent column!");
+        assert_eq!(error(PART2_RULES.replace("opponent, outcome", "opponent, player, outcome")),
+            "Rules must have exactly one player or outcome column!");
+    }
+}
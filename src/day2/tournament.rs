@@ -0,0 +1,303 @@
+use crate::prelude::*;
+use std::fmt;
+use std::cmp::Ordering;
+use super::{RPS, RPS::*, GameResult, GameResult::*, score_game, game_result, get_winning_move};
+use super::rules::RuleSet;
+use crate::rng::Rng;
+
+/**/
+// A round-robin tournament between rock-paper-scissors bots.
+// Every pair of players meets for a number of games of a fixed number of rounds, scored with `score_game`.
+// All randomness comes from a seed, so a tournament with the same players and seed always gives the same results.
+/**/
+
+fn random_move(rng: &mut Rng) -> RPS {
+    [Rock, Paper, Scissors][rng.below(3) as usize]
+}
+
+/// A rock-paper-scissors bot.
+pub trait Player {
+    fn name(&self) -> String;
+    /// Forget everything learned in the previous game. `seed` is unique to the upcoming game.
+    fn reset(&mut self, seed: u64);
+    fn pick(&mut self) -> RPS;
+    /// Called after every round with both moves.
+    fn observe(&mut self, my_pick: RPS, opp_pick: RPS);
+}
+
+/// Always plays the same move.
+pub struct FixedPlayer(RPS);
+impl Player for FixedPlayer {
+    fn name(&self) -> String { format!("Fixed({:?})", self.0) }
+    fn reset(&mut self, _seed: u64) {}
+    fn pick(&mut self) -> RPS { self.0 }
+    fn observe(&mut self, _my_pick: RPS, _opp_pick: RPS) {}
+}
+
+/// Plays uniformly at random. The player's own seed is mixed with the game seed, so two random players
+/// with different seeds don't mirror each other.
+pub struct RandomPlayer {
+    seed: u64,
+    rng: Rng,
+}
+impl RandomPlayer {
+    pub fn new(seed: u64) -> RandomPlayer {
+        RandomPlayer { seed, rng: Rng::new(seed) }
+    }
+}
+impl Player for RandomPlayer {
+    fn name(&self) -> String { format!("Random({})", self.seed) }
+    fn reset(&mut self, seed: u64) { self.rng = Rng::new(seed ^ self.seed.rotate_left(32)) }
+    fn pick(&mut self) -> RPS { random_move(&mut self.rng) }
+    fn observe(&mut self, _my_pick: RPS, _opp_pick: RPS) {}
+}
+
+/// Counts the opponent's moves and plays whatever beats their most common one.
+/// Ties are broken in the order rock, paper, scissors.
+#[derive(Default)]
+pub struct FrequencyPlayer {
+    counts: [usize; 3],
+}
+impl Player for FrequencyPlayer {
+    fn name(&self) -> String { "FrequencyCounter".to_string() }
+    fn reset(&mut self, _seed: u64) { self.counts = [0; 3] }
+    fn pick(&mut self) -> RPS {
+        let mut most_common = Rock;
+        for opp_pick in [Paper, Scissors] {
+            if self.counts[opp_pick as usize - 1] > self.counts[most_common as usize - 1] {
+                most_common = opp_pick;
+            }
+        }
+        get_winning_move(most_common)
+    }
+    fn observe(&mut self, _my_pick: RPS, opp_pick: RPS) {
+        self.counts[opp_pick as usize - 1] += 1;
+    }
+}
+
+/// Keeps its move after a win or draw. After a loss, switches to whatever would have beaten the opponent.
+pub struct WinStayLoseShiftPlayer {
+    first: RPS,
+    next: RPS,
+}
+impl WinStayLoseShiftPlayer {
+    pub fn new(first: RPS) -> WinStayLoseShiftPlayer {
+        WinStayLoseShiftPlayer { first, next: first }
+    }
+}
+impl Player for WinStayLoseShiftPlayer {
+    fn name(&self) -> String { "WinStayLoseShift".to_string() }
+    fn reset(&mut self, _seed: u64) { self.next = self.first }
+    fn pick(&mut self) -> RPS { self.next }
+    fn observe(&mut self, my_pick: RPS, opp_pick: RPS) {
+        if game_result(my_pick, opp_pick) == Loss {
+            self.next = get_winning_move(opp_pick);
+        }
+    }
+}
+
+/// Replays our moves from a strategy guide, starting again from the top when it runs out.
+pub struct ReplayPlayer {
+    name: String,
+    moves: Vec<RPS>,
+    position: usize,
+}
+impl ReplayPlayer {
+    /// Read our moves from a guide, interpreting it with the given rules.
+    pub fn from_guide(path: &str, rules: &RuleSet) -> Result<ReplayPlayer> {
+        let contents = std::fs::read_to_string(path)?;
+        let moves = contents.lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| rules.interpret(line).map(|(my_pick, _)| my_pick))
+            .collect::<Result<Vec<RPS>>>()?;
+        if moves.is_empty() {
+            return Err(anyhow!("Guide '{path}' contains no moves!"));
+        }
+        Ok(ReplayPlayer { name: format!("Replay({path})"), moves, position: 0 })
+    }
+}
+impl Player for ReplayPlayer {
+    fn name(&self) -> String { self.name.clone() }
+    fn reset(&mut self, _seed: u64) { self.position = 0 }
+    fn pick(&mut self) -> RPS {
+        let pick = self.moves[self.position];
+        self.position = (self.position + 1) % self.moves.len();
+        pick
+    }
+    fn observe(&mut self, _my_pick: RPS, _opp_pick: RPS) {}
+}
+
+/// Derive a seed for one game from the tournament seed and which game it is.
+fn game_seed(seed: u64, player1: usize, player2: usize, game: usize) -> u64 {
+    let mut rng = Rng::new(seed ^ ((player1 as u64) << 48) ^ ((player2 as u64) << 32) ^ game as u64);
+    rng.next_u64()
+}
+
+/// Play one game between two players, returning each player's total score.
+fn play_game(player1: &mut dyn Player, player2: &mut dyn Player, rounds: usize, seed: u64) -> (usize, usize) {
+    player1.reset(seed);
+    player2.reset(seed.rotate_left(1));
+    let mut scores = (0, 0);
+    for _ in 0..rounds {
+        let (pick1, pick2) = (player1.pick(), player2.pick());
+        scores.0 += score_game(pick1, pick2);
+        scores.1 += score_game(pick2, pick1);
+        player1.observe(pick1, pick2);
+        player2.observe(pick2, pick1);
+    }
+    scores
+}
+
+/// A player's overall record.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Standing {
+    pub name: String,
+    pub total_score: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// The score of every game played, sorted ascending.
+    pub game_scores: Vec<usize>,
+}
+impl Standing {
+    fn record(&mut self, result: GameResult) {
+        match result {
+            Win => self.wins += 1,
+            Draw => self.draws += 1,
+            Loss => self.losses += 1,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TournamentResults {
+    /// In the order the players were given.
+    pub standings: Vec<Standing>,
+    /// head_to_head[i][j] is the number of games player i won against player j.
+    pub head_to_head: Vec<Vec<usize>>,
+}
+
+/// Every player plays every other player `games` times, each game lasting `rounds` rounds.
+pub fn round_robin(players: &mut [Box<dyn Player>], games: usize, rounds: usize, seed: u64) -> TournamentResults {
+    let n = players.len();
+    let mut standings: Vec<Standing> = players.iter()
+        .map(|p| Standing { name: p.name(), ..Default::default() })
+        .collect();
+    let mut head_to_head = vec![vec![0; n]; n];
+
+    for i in 0..n {
+        for j in i+1..n {
+            // players[i] and players[j] are both mutably borrowed, so split the slice between them.
+            let (left, right) = players.split_at_mut(j);
+            let (player1, player2) = (&mut left[i], &mut right[0]);
+
+            for game in 0..games {
+                let (score1, score2) = play_game(player1.as_mut(), player2.as_mut(), rounds, game_seed(seed, i, j, game));
+                standings[i].total_score += score1;
+                standings[j].total_score += score2;
+                standings[i].game_scores.push(score1);
+                standings[j].game_scores.push(score2);
+                match score1.cmp(&score2) {
+                    Ordering::Greater => {
+                        head_to_head[i][j] += 1;
+                        standings[i].record(Win);
+                        standings[j].record(Loss);
+                    },
+                    Ordering::Less => {
+                        head_to_head[j][i] += 1;
+                        standings[i].record(Loss);
+                        standings[j].record(Win);
+                    },
+                    Ordering::Equal => {
+                        standings[i].record(Draw);
+                        standings[j].record(Draw);
+                    },
+                }
+            }
+        }
+    }
+
+    for standing in standings.iter_mut() {
+        standing.game_scores.sort_unstable();
+    }
+    TournamentResults { standings, head_to_head }
+}
+
+impl fmt::Display for TournamentResults {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Rank by games won, then by total score.
+        let mut ranking: Vec<&Standing> = self.standings.iter().collect();
+        ranking.sort_by(|a, b| b.wins.cmp(&a.wins).then(b.total_score.cmp(&a.total_score)));
+
+        writeln!(f, "Standings:")?;
+        writeln!(f, "  {:<24} {:>5} {:>5} {:>6} {:>10}", "Player", "Won", "Drew", "Lost", "Score")?;
+        for s in &ranking {
+            writeln!(f, "  {:<24} {:>5} {:>5} {:>6} {:>10}", s.name, s.wins, s.draws, s.losses, s.total_score)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Head to head (games won by row against column):")?;
+        write!(f, "  {:<24}", "")?;
+        for j in 0..self.standings.len() {
+            write!(f, " {:>5}", j + 1)?;
+        }
+        writeln!(f)?;
+        for (i, row) in self.head_to_head.iter().enumerate() {
+            write!(f, "  {:<24}", format!("{} {}", i + 1, self.standings[i].name))?;
+            for (j, won) in row.iter().enumerate() {
+                if i == j { write!(f, " {:>5}", "-")? } else { write!(f, " {won:>5}")? }
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Game score distribution:")?;
+        writeln!(f, "  {:<24} {:>6} {:>6} {:>8} {:>6} {:>6}", "Player", "Min", "Q1", "Median", "Q3", "Max")?;
+        for s in &self.standings {
+            let scores = &s.game_scores;
+            if scores.is_empty() {continue}
+            let quantile = |q: usize| scores[(scores.len() - 1) * q / 4];
+            writeln!(f, "  {:<24} {:>6} {:>6} {:>8} {:>6} {:>6}",
+                s.name, quantile(0), quantile(1), quantile(2), quantile(3), quantile(4))?;
+        }
+        Ok(())
+    }
+}
+
+/// The standard lineup: one of each kind of player, with the replay player following the puzzle guide under part 1 rules.
+pub fn default_players(seed: u64) -> Result<Vec<Box<dyn Player>>> {
+    Ok(vec![
+        Box::new(FixedPlayer(Rock)),
+        Box::new(FixedPlayer(Paper)),
+        Box::new(FixedPlayer(Scissors)),
+        Box::new(RandomPlayer::new(seed)),
+        Box::new(FrequencyPlayer::default()),
+        Box::new(WinStayLoseShiftPlayer::new(Rock)),
+        Box::new(ReplayPlayer::from_guide("input/day2.txt", &RuleSet::part1())?),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_results() {
+        let run = |seed| round_robin(&mut default_players(seed).unwrap(), 5, 50, seed);
+        let (first, second) = (run(7), run(7));
+        assert_eq!(first.standings, second.standings);
+        assert_eq!(first.head_to_head, second.head_to_head);
+        assert_ne!(run(8), first);
+    }
+
+    #[test]
+    fn paper_beats_rock_every_time() {
+        let mut players: Vec<Box<dyn Player>> = vec![Box::new(FixedPlayer(Rock)), Box::new(FixedPlayer(Paper))];
+        let results = round_robin(&mut players, 2, 10, 0);
+        // Rock scores 1 + 0 a round, and paper 2 + 6.
+        assert_eq!(results.standings[0].game_scores, [10, 10]);
+        assert_eq!(results.standings[1].game_scores, [80, 80]);
+        assert_eq!((results.standings[1].wins, results.standings[0].losses), (2, 2));
+        assert_eq!(results.head_to_head, [[0, 0], [2, 0]]);
+    }
+}
@@ -1,41 +1,121 @@
 use crate::prelude::*;
 
-// Assumes input is only ASCII upper/lowercase.
+pub mod items;
+pub mod validate;
+pub mod badges;
+pub mod batched;
+pub mod reorganise;
+use items::{Alphabet, ItemSet};
+
+/// How to treat rucksacks that don't follow the puzzle's rules.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Mode {
+    /// Every rucksack (or group) must share exactly one item, and every rucksack must have an even number of items.
+    Strict,
+    /// Add up every shared item, whether there are none or several.
+    Lenient,
+}
+
+/// The items shared between the compartments of one rucksack, or between the rucksacks of one group.
+#[derive(Clone, Debug)]
+pub struct Shared {
+    /// The (first) line of the input this came from, starting at 1.
+    pub line_no: usize,
+    /// Each shared item once, in ascending priority order.
+    pub items: Vec<char>,
+    pub priorities: Vec<usize>,
+}
+
+/// Something wrong with a rucksack or group.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Problem {
+    NoSharedItem,
+    MultipleSharedItems(Vec<char>),
+    /// The rucksack has this many items, so can't be split into equal compartments.
+    OddLength(usize),
+    /// The group only has this many rucksacks.
+    IncompleteGroup(usize),
+}
+
+// The puzzle input only uses ASCII upper/lowercase.
 pub fn calculate_part1() -> Result<usize>{
-    let file = File::open("input/day3.txt")?;
-    let reader = BufReader::new(file);
+    calculate_part1_with("input/day3.txt", &Alphabet::letters(), Mode::Strict)
+}
+
+// Same as part 1, but for any input file, alphabet and mode.
+pub fn calculate_part1_with(path: &str, alphabet: &Alphabet, mode: Mode) -> Result<usize>{
     let mut priority_sum: usize = 0;
+    for (shared, problems) in shared_per_rucksack(path, alphabet)? {
+        if mode == Mode::Strict {
+            if let Some(problem) = problems.first() {
+                return Err(anyhow!("Line {}: {problem}", shared.line_no));
+            }
+        }
+        priority_sum += shared.priorities.iter().sum::<usize>();
+    }
+    Ok(priority_sum)
+}
+
+/// Find the items shared by both compartments of every rucksack in the file, and anything wrong with each rucksack.
+pub fn shared_per_rucksack(path: &str, alphabet: &Alphabet) -> Result<Vec<(Shared, Vec<Problem>)>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut rucksacks = vec![];
 
-    for line in reader.lines() {
+    for (n, line) in reader.lines().enumerate() {
         let line = line?;
         if line.is_empty() {continue}
 
-        let mut bitmasks: [u64; 2] = [0; 2];
-        let (comp1, comp2) = line.split_at(line.len()/2);
+        // Split by characters rather than bytes, so multi-byte items aren't cut in half.
+        let item_count = line.chars().count();
+        let split = line.char_indices().nth(item_count/2).map_or(line.len(), |(i, _)| i);
+        let (comp1, comp2) = line.split_at(split);
 
-        // For each half, loop through the characters in the string. Convert each to a priority, 
-        // then encode in a bitmask by putting a 1 in that position
-        for (bitmask, compartment) in bitmasks.iter_mut().zip([comp1, comp2]) {
-            for char in compartment.as_bytes() {
-                let priority = to_priority(char);
-                *bitmask |= 1<<priority;
-            }
+        // For each half, convert each item to a priority and add it to that half's set.
+        let item_sets = [encode_rucksack(comp1, alphabet)?, encode_rucksack(comp2, alphabet)?];
+
+        // Items common to both halves.
+        let common_items = item_sets[0].intersection(&item_sets[1]);
+
+        let shared = to_shared(n + 1, &common_items, &line, alphabet)?;
+        let mut problems = shared_item_problems(&shared);
+        if item_count % 2 != 0 {
+            problems.push(Problem::OddLength(item_count));
         }
+        rucksacks.push((shared, problems));
+    }
+    Ok(rucksacks)
+}
 
-        // Items common to both elements survive a bitwise AND.
-        let common_items_bitmask = bitmasks[0] & bitmasks[1];
+// Convert lowercase ascii to 1-26 and uppercase to 27-52. Anything else has no priority.
+fn to_priority(item: char) -> Option<usize> {
+    match item {
+        'a'..='z' => Some(item as usize - 'a' as usize + 1),
+        'A'..='Z' => Some(item as usize - 'A' as usize + 27),
+        _ => None,
+    }
+}
 
-        // Assume there is only one shared item => only one non-zero bit => power of two => priority of item is log2.
-        let common_item_priority = common_items_bitmask.ilog2() as usize;
-        priority_sum += common_item_priority;
+/// Look up which items in `contents` have the priorities in `common_items`.
+fn to_shared(line_no: usize, common_items: &ItemSet, contents: &str, alphabet: &Alphabet) -> Result<Shared> {
+    let priorities = common_items.priorities();
+    let mut items = vec![];
+    for &priority in &priorities {
+        for item in contents.chars() {
+            if alphabet.priority(item)? == priority && !items.contains(&item) {
+                items.push(item);
+            }
+        }
     }
-    Ok(priority_sum)
+    Ok(Shared { line_no, items, priorities })
 }
 
-// Assumes input is only ASCII upper/lowercase.
-fn to_priority(ascii: &u8) -> u8 {
-    // Some good ol' magic numbers. Convert lowercase ascii from 97+ to 1+, and uppercase from 65+ to 27+
-    if ascii <= &90 {ascii - 38} else {ascii - 96}
+fn shared_item_problems(shared: &Shared) -> Vec<Problem> {
+    match shared.items.len() {
+        0 => vec![Problem::NoSharedItem],
+        1 => vec![],
+        _ => vec![Problem::MultipleSharedItems(shared.items.clone())],
+    }
 }
 
 /********** Part 2 **********/
@@ -44,38 +124,112 @@ fn to_priority(ascii: &u8) -> u8 {
 // Assumes input is a multiple of three lines.
 const GROUP_SIZE: usize = 3;
 pub fn calculate_part2() -> Result<usize>{
-    let file = File::open("input/day3.txt")?;
-    let reader = BufReader::new(file);
+    calculate_part2_with("input/day3.txt", &Alphabet::letters(), Mode::Strict)
+}
+
+pub fn calculate_part2_with(path: &str, alphabet: &Alphabet, mode: Mode) -> Result<usize>{
     let mut priority_sum: usize = 0;
+    for (shared, problems) in shared_per_group(path, alphabet)? {
+        if mode == Mode::Strict {
+            if let Some(problem) = problems.first() {
+                return Err(anyhow!("Group starting at line {}: {problem}", shared.line_no));
+            }
+        }
+        priority_sum += shared.priorities.iter().sum::<usize>();
+    }
+    Ok(priority_sum)
+}
+
+/// Find the items shared by every rucksack in each group, and anything wrong with each group.
+pub fn shared_per_group(path: &str, alphabet: &Alphabet) -> Result<Vec<(Shared, Vec<Problem>)>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
 
     let lines = reader.lines()
         .map_while(Result::ok)
-        .collect::<Vec<String>>();
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .collect::<Vec<(usize, String)>>();
 
+    let mut groups = vec![];
     for group in lines.chunks(GROUP_SIZE) {
-        let mut bitmasks: [u64; GROUP_SIZE] = [0; GROUP_SIZE];
-        for (bitmask, rucksack) in bitmasks.iter_mut().zip(group){
-            *bitmask = encode_rucksack(rucksack);
-        }
+        let item_sets = group.iter()
+            .map(|(_, rucksack)| encode_rucksack(rucksack, alphabet))
+            .collect::<Result<Vec<ItemSet>>>()?;
 
-        // Items common to all elements survive a bitwise AND.
-        let common_items_bitmask = bitmasks.into_iter()
-            .reduce(|msk1, msk2| msk1 & msk2)
+        // Items common to all elements.
+        let common_items = item_sets.into_iter()
+            .reduce(|set1, set2| set1.intersection(&set2))
             .ok_or(anyhow!("Reducing failed!"))?;
 
-        // Assume there is only one shared item => only one non-zero bit => power of two => priority of item is log2.
-        let common_item_priority = common_items_bitmask.ilog2() as usize;
-        priority_sum += common_item_priority;
+        let (first_line, first_rucksack) = &group[0];
+        let shared = to_shared(first_line + 1, &common_items, first_rucksack, alphabet)?;
+        let mut problems = shared_item_problems(&shared);
+        if group.len() != GROUP_SIZE {
+            problems.push(Problem::IncompleteGroup(group.len()));
+        }
+        groups.push((shared, problems));
     }
-    Ok(priority_sum)
+    Ok(groups)
 }
 
-/// Encode a rucksack into a bitmask where each index of the mask is whether an element is present.
-fn encode_rucksack(rucksack: &str) -> u64 {
-    let mut bitmask: u64 = 0;
-    for char in rucksack.as_bytes() {
-        let priority = to_priority(char);
-        bitmask |= 1<<priority;
+/// Encode a rucksack into a set of the priorities of the items it contains.
+fn encode_rucksack(rucksack: &str, alphabet: &Alphabet) -> Result<ItemSet> {
+    let mut items = alphabet.empty_set();
+    for item in rucksack.chars() {
+        items.insert(alphabet.priority(item)?);
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::with_input;
+
+    #[test]
+    fn reports_rucksack_problems() {
+        // No shared item, several, an odd length with one, and an ordinary rucksack.
+        with_input("day3_rucksacks", "abcd\nabcabc\naba\nvJrwpWtwJgWrhcsFMMfFFhFp\n", |path| {
+            let alphabet = Alphabet::letters();
+            let rucksacks = shared_per_rucksack(path, &alphabet).unwrap();
+            let problems: Vec<Vec<Problem>> = rucksacks.iter().map(|(_, problems)| problems.clone()).collect();
+            assert_eq!(problems, vec![
+                vec![Problem::NoSharedItem],
+                vec![Problem::MultipleSharedItems(vec!['a', 'b', 'c'])],
+                vec![Problem::OddLength(3)],
+                vec![],
+            ]);
+            assert_eq!(rucksacks[3].0.items, ['p']);
+
+            assert_eq!(calculate_part1_with(path, &alphabet, Mode::Lenient).unwrap(), 1 + 2 + 3 + 1 + 16);
+            let error = calculate_part1_with(path, &alphabet, Mode::Strict).unwrap_err();
+            assert!(error.to_string().starts_with("Line 1:"));
+        });
     }
-    bitmask
-}
\ No newline at end of file
+
+    #[test]
+    fn reports_group_problems() {
+        // One full group sharing only 'b', then a group of one.
+        with_input("day3_groups", "abX\ncbY\ndbZ\n\naa\n", |path| {
+            let alphabet = Alphabet::letters();
+            let groups = shared_per_group(path, &alphabet).unwrap();
+            assert_eq!(groups[0].0.items, ['b']);
+            assert_eq!(groups[0].1, []);
+            assert_eq!((groups[1].0.line_no, &groups[1].1), (5, &vec![Problem::IncompleteGroup(1)]));
+
+            assert_eq!(calculate_part2_with(path, &alphabet, Mode::Lenient).unwrap(), 2 + 1);
+            assert!(calculate_part2_with(path, &alphabet, Mode::Strict).is_err());
+        });
+    }
+
+    #[test]
+    fn empty_input_sums_to_zero() {
+        with_input("empty", "", |path| {
+            let alphabet = Alphabet::letters();
+            assert!(shared_per_rucksack(path, &alphabet).unwrap().is_empty());
+            assert_eq!(calculate_part1_with(path, &alphabet, Mode::Strict).unwrap(), 0);
+            assert_eq!(calculate_part2_with(path, &alphabet, Mode::Strict).unwrap(), 0);
+        });
+    }
+}
@@ -0,0 +1,314 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{encode_rucksack, to_shared};
+use super::items::{Alphabet, ItemSet};
+
+/**/
+// Part 2 assumes each group of elves is listed together. When the rucksacks are in any order, we have to
+// find a partition into groups of k where every group shares exactly one badge item.
+// This is an exact-cover problem, solved with Knuth's Algorithm X: list every group of k rucksacks sharing
+// exactly one item, then repeatedly pick the ungrouped rucksack with the fewest remaining candidate groups
+// and try each of them, backtracking on failure.
+/**/
+
+/// Whether the partition found is the only one.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Uniqueness {
+    Unique,
+    Multiple,
+    /// The node budget ran out before the search could tell.
+    Unknown,
+}
+
+/// One group of rucksacks, by index into the input, along with the badge they share.
+#[derive(Clone, Debug)]
+pub struct BadgeGroup {
+    pub rucksacks: Vec<usize>,
+    pub badge: char,
+    pub priority: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Discovery {
+    /// The first partition found, if any.
+    pub groups: Option<Vec<BadgeGroup>>,
+    pub uniqueness: Uniqueness,
+    /// Search nodes visited, including those spent listing candidate groups.
+    pub nodes: usize,
+}
+
+/// Every group of `group_size` rucksacks sharing exactly one item, or None if the budget runs out first.
+/// Adding rucksacks can only shrink the shared items, so stop extending a group as soon as it's empty.
+fn candidate_groups(sets: &[ItemSet], group_size: usize, nodes: &mut usize, budget: usize) -> Option<Vec<Vec<usize>>> {
+    /// Returns false if the budget ran out.
+    fn extend(sets: &[ItemSet], group_size: usize, group: &mut Vec<usize>, common: &ItemSet,
+              candidates: &mut Vec<Vec<usize>>, nodes: &mut usize, budget: usize) -> bool {
+        if *nodes >= budget {return false}
+        *nodes += 1;
+        if group.len() == group_size {
+            if common.len() == 1 {
+                candidates.push(group.clone());
+            }
+            return true;
+        }
+        for next in group[group.len() - 1] + 1..sets.len() {
+            let common = common.intersection(&sets[next]);
+            if common.is_empty() {continue}
+            group.push(next);
+            let finished = extend(sets, group_size, group, &common, candidates, nodes, budget);
+            group.pop();
+            if !finished {return false}
+        }
+        true
+    }
+
+    let mut candidates = vec![];
+    for (first, set) in sets.iter().enumerate() {
+        if !extend(sets, group_size, &mut vec![first], set, &mut candidates, nodes, budget) {
+            return None;
+        }
+    }
+    Some(candidates)
+}
+
+struct Search {
+    candidates: Vec<Vec<usize>>,
+    /// containing[r] lists the candidates that rucksack r is in.
+    containing: Vec<Vec<usize>>,
+    /// A candidate is live while none of its rucksacks are in a chosen group.
+    live: Vec<bool>,
+    /// live_count[r] is the number of live candidates containing rucksack r.
+    live_count: Vec<usize>,
+    grouped: Vec<bool>,
+    chosen: Vec<usize>,
+    first_solution: Option<Vec<usize>>,
+    solutions: usize,
+    nodes: usize,
+    budget: usize,
+    /// A node needed visiting after the budget was used up.
+    exhausted: bool,
+}
+
+impl Search {
+    /// Stop once we know whether the answer is unique, or we're out of budget.
+    fn done(&self) -> bool {
+        self.solutions >= 2 || self.exhausted
+    }
+
+    /// Choose `candidate`, killing every other candidate that shares a rucksack with it. Returns what was killed.
+    fn choose(&mut self, candidate: usize) -> Vec<usize> {
+        let mut killed = vec![];
+        for &member in &self.candidates[candidate] {
+            self.grouped[member] = true;
+            for &other in &self.containing[member] {
+                if !self.live[other] {continue}
+                self.live[other] = false;
+                for &affected in &self.candidates[other] {
+                    self.live_count[affected] -= 1;
+                }
+                killed.push(other);
+            }
+        }
+        self.chosen.push(candidate);
+        killed
+    }
+
+    fn unchoose(&mut self, candidate: usize, killed: Vec<usize>) {
+        self.chosen.pop();
+        for other in killed {
+            self.live[other] = true;
+            for &affected in &self.candidates[other] {
+                self.live_count[affected] += 1;
+            }
+        }
+        for &member in &self.candidates[candidate] {
+            self.grouped[member] = false;
+        }
+    }
+
+    fn search(&mut self) {
+        // Only give up on a node that needs visiting, so a search that fits the budget exactly still finishes.
+        if self.nodes >= self.budget {
+            self.exhausted = true;
+            return;
+        }
+        self.nodes += 1;
+        // The most constrained ungrouped rucksack. If there isn't one, every rucksack is in a group.
+        let Some(rucksack) = (0..self.grouped.len())
+            .filter(|&r| !self.grouped[r])
+            .min_by_key(|&r| self.live_count[r]) else {
+            self.solutions += 1;
+            if self.first_solution.is_none() {
+                self.first_solution = Some(self.chosen.clone());
+            }
+            return;
+        };
+
+        let options: Vec<usize> = self.containing[rucksack].iter().copied().filter(|&c| self.live[c]).collect();
+        for candidate in options {
+            if self.done() {return}
+            let killed = self.choose(candidate);
+            self.search();
+            self.unchoose(candidate, killed);
+        }
+    }
+}
+
+/// Partition `rucksacks` into groups of `group_size` that each share exactly one item, visiting at most
+/// `budget` search nodes.
+pub fn discover_groups(rucksacks: &[String], group_size: usize, alphabet: &Alphabet, budget: usize) -> Result<Discovery> {
+    if group_size == 0 || !rucksacks.len().is_multiple_of(group_size) {
+        return Err(anyhow!("{} rucksacks can't be split into groups of {group_size}!", rucksacks.len()));
+    }
+    let sets = rucksacks.iter()
+        .map(|rucksack| encode_rucksack(rucksack, alphabet))
+        .collect::<Result<Vec<ItemSet>>>()?;
+
+    let mut nodes = 0;
+    let candidates = candidate_groups(&sets, group_size, &mut nodes, budget);
+    let exhausted = candidates.is_none();
+    let candidates = candidates.unwrap_or_default();
+    let mut containing = vec![vec![]; sets.len()];
+    for (c, candidate) in candidates.iter().enumerate() {
+        for &member in candidate {
+            containing[member].push(c);
+        }
+    }
+    let mut search = Search {
+        live: vec![true; candidates.len()],
+        live_count: containing.iter().map(Vec::len).collect(),
+        candidates,
+        containing,
+        grouped: vec![false; sets.len()],
+        chosen: vec![],
+        first_solution: None,
+        solutions: 0,
+        nodes,
+        budget,
+        exhausted,
+    };
+    if !exhausted {
+        search.search();
+    }
+
+    let uniqueness = match search.solutions {
+        n if n >= 2 => Uniqueness::Multiple,
+        _ if search.exhausted => Uniqueness::Unknown,
+        // Exactly one partition, or none at all and so nothing to be ambiguous about.
+        _ => Uniqueness::Unique,
+    };
+
+    let mut groups = search.first_solution.map(|solution| {
+        solution.into_iter().map(|c| {
+            let members = search.candidates[c].clone();
+            let common = members.iter()
+                .map(|&i| sets[i].clone())
+                .reduce(|set1, set2| set1.intersection(&set2))
+                .expect("Groups are never empty");
+            let shared = to_shared(0, &common, &rucksacks[members[0]], alphabet)?;
+            Ok(BadgeGroup { rucksacks: members, badge: shared.items[0], priority: shared.priorities[0] })
+        }).collect::<Result<Vec<BadgeGroup>>>()
+    }).transpose()?;
+    if let Some(groups) = groups.as_mut() {
+        groups.sort_by_key(|g| g.rucksacks[0]);
+    }
+
+    Ok(Discovery { groups, uniqueness, nodes: search.nodes })
+}
+
+pub fn discover_groups_in_file(path: &str, group_size: usize, alphabet: &Alphabet, budget: usize) -> Result<Discovery> {
+    let contents = std::fs::read_to_string(path)?;
+    let rucksacks: Vec<String> = contents.lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    discover_groups(&rucksacks, group_size, alphabet, budget)
+}
+
+impl fmt::Display for Discovery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(groups) = &self.groups else {
+            return match self.uniqueness {
+                Uniqueness::Unknown => writeln!(f, "No partition found within {} nodes.", self.nodes),
+                _ => writeln!(f, "No partition exists ({} nodes searched).", self.nodes),
+            };
+        };
+        let uniqueness = match self.uniqueness {
+            Uniqueness::Unique => "unique",
+            Uniqueness::Multiple => "not unique",
+            Uniqueness::Unknown => "uniqueness unknown, search budget exhausted",
+        };
+        writeln!(f, "Found {} groups ({uniqueness}, {} nodes searched)", groups.len(), self.nodes)?;
+        writeln!(f, "Badge priority sum: {}", groups.iter().map(|g| g.priority).sum::<usize>())?;
+        for group in groups {
+            // Report rucksacks by their position among the non-empty lines, starting at 1.
+            let members: Vec<String> = group.rucksacks.iter().map(|i| (i + 1).to_string()).collect();
+            writeln!(f, "  {} -> {}", members.join(", "), group.badge)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn discover(rucksacks: &[&str], group_size: usize, budget: usize) -> Discovery {
+        let rucksacks: Vec<String> = rucksacks.iter().map(|r| r.to_string()).collect();
+        discover_groups(&rucksacks, group_size, &Alphabet::letters(), budget).unwrap()
+    }
+
+    #[test]
+    fn finds_a_unique_partition() {
+        // Two groups of three, shuffled together.
+        let discovery = discover(&["aB", "xD", "aF", "xH", "aJ", "xL"], 3, 10_000);
+        assert_eq!(discovery.uniqueness, Uniqueness::Unique);
+        let groups = discovery.groups.unwrap();
+        let found: Vec<(Vec<usize>, char)> = groups.into_iter().map(|g| (g.rucksacks, g.badge)).collect();
+        assert_eq!(found, vec![(vec![0, 2, 4], 'a'), (vec![1, 3, 5], 'x')]);
+    }
+
+    #[test]
+    fn notices_other_partitions() {
+        // Any two of these make a group.
+        let discovery = discover(&["aB", "aC", "aD", "aE"], 2, 10_000);
+        assert_eq!(discovery.uniqueness, Uniqueness::Multiple);
+        assert_eq!(discovery.groups.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn reports_impossible_partitions() {
+        // 'a' is shared by three rucksacks, so one of them is always left with 'd' or 'e', which share nothing.
+        let discovery = discover(&["ab", "ac", "ad", "e"], 2, 10_000);
+        assert!(discovery.groups.is_none());
+        assert_eq!(discovery.uniqueness, Uniqueness::Unique);
+        assert!(discovery.to_string().starts_with("No partition exists"));
+    }
+
+    #[test]
+    fn gives_up_when_out_of_budget() {
+        let discovery = discover(&["aB", "aC", "aD", "aE"], 2, 3);
+        assert_eq!(discovery.uniqueness, Uniqueness::Unknown);
+        assert!(discovery.nodes <= 3);
+    }
+
+    #[test]
+    fn exact_budget_is_enough() {
+        for (rucksacks, group_size, uniqueness) in [
+            (&["aB", "xD", "aF", "xH", "aJ", "xL"][..], 3, Uniqueness::Unique),
+            (&["aB", "aC", "aD", "aE"][..], 2, Uniqueness::Multiple),
+            (&["ab", "ac", "ad", "e"][..], 2, Uniqueness::Unique),
+        ] {
+            let needed = discover(rucksacks, group_size, 10_000).nodes;
+            let exact = discover(rucksacks, group_size, needed);
+            assert_eq!((exact.uniqueness, exact.nodes), (uniqueness, needed));
+            assert_eq!(discover(rucksacks, group_size, needed - 1).uniqueness, Uniqueness::Unknown);
+        }
+    }
+
+    #[test]
+    fn rejects_uneven_groups() {
+        let rucksacks = vec!["a".to_string(); 5];
+        assert!(discover_groups(&rucksacks, 2, &Alphabet::letters(), 100).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::prelude::*;
+use std::fmt;
+use std::time::{Duration, Instant};
+use super::{Mode, Problem, GROUP_SIZE, calculate_part1_with, calculate_part2_with, to_shared, shared_item_problems};
+use super::items::Alphabet;
+
+/**/
+// A high-throughput version of both parts for very large rucksack files.
+// The whole file is read into one buffer and split into byte slices, so there's no per-line allocation.
+// Each byte is converted to its bit with a 256-entry lookup table, ORed into eight independent accumulators
+// so the loop has no dependency chain and the compiler can vectorise it.
+// Only works for ASCII alphabets that fit in a u64, which covers the puzzle.
+/**/
+
+/// Lines are encoded this many at a time before their masks are combined.
+const BATCH_SIZE: usize = 64;
+/// Set in a lookup table entry for bytes that aren't in the alphabet. Priority 0 is never a real item's.
+const INVALID: u64 = 1;
+
+/// LUT[byte] is the bit for that byte's priority, or INVALID.
+struct Lut([u64; 256]);
+
+impl Lut {
+    fn new(alphabet: &Alphabet) -> Result<Lut> {
+        let mut lut = [INVALID; 256];
+        for byte in 0..128u8 {
+            if let Ok(priority) = alphabet.priority(byte as char) {
+                if priority == 0 || priority >= 64 {
+                    return Err(anyhow!("The batched kernel needs priorities from 1 to 63, but '{}' has {priority}!", byte as char));
+                }
+                lut[byte as usize] = 1 << priority;
+            }
+        }
+        Ok(Lut(lut))
+    }
+
+    /// Encode a rucksack into a bitmask, with INVALID set if any item isn't in the alphabet.
+    #[inline]
+    fn encode(&self, rucksack: &[u8]) -> u64 {
+        let mut accumulators = [0u64; 8];
+        let chunks = rucksack.chunks_exact(8);
+        let remainder = chunks.remainder();
+        for chunk in chunks {
+            for (accumulator, &byte) in accumulators.iter_mut().zip(chunk) {
+                *accumulator |= self.0[byte as usize];
+            }
+        }
+        let mut bitmask = accumulators.iter().fold(0, |msk1, msk2| msk1 | msk2);
+        for &byte in remainder {
+            bitmask |= self.0[byte as usize];
+        }
+        bitmask
+    }
+}
+
+/// Non-empty lines of the buffer with their line numbers (starting at 1), without line endings.
+fn lines(buffer: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    buffer.split(|&byte| byte == b'\n')
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.strip_suffix(b"\r").unwrap_or(line)))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// Add up the priorities in `common_items`.
+fn priority_sum_of(common_items: u64) -> usize {
+    let mut remaining = common_items;
+    let mut sum = 0;
+    while remaining != 0 {
+        sum += remaining.trailing_zeros() as usize;
+        remaining &= remaining - 1;
+    }
+    sum
+}
+
+/// Give the same error as the scalar path for a rucksack with an item the lookup table doesn't have.
+fn unmapped_item(rucksack: &[u8], alphabet: &Alphabet) -> Result<()> {
+    for item in std::str::from_utf8(rucksack)?.chars() {
+        alphabet.priority(item)?;
+    }
+    // Every item is in the alphabet, but not all of them are ASCII.
+    Err(anyhow!("The batched kernel only handles ASCII items!"))
+}
+
+/// What the scalar path finds wrong with `common_items`, using `contents` to name the items.
+fn shared_item_problem(common_items: u64, contents: &[u8], alphabet: &Alphabet) -> Result<Option<Problem>> {
+    if common_items.count_ones() == 1 {
+        return Ok(None);
+    }
+    let mut items = alphabet.empty_set();
+    let mut remaining = common_items;
+    while remaining != 0 {
+        items.insert(remaining.trailing_zeros() as usize);
+        remaining &= remaining - 1;
+    }
+    let shared = to_shared(0, &items, std::str::from_utf8(contents)?, alphabet)?;
+    Ok(shared_item_problems(&shared).into_iter().next())
+}
+
+/// Same answer as `calculate_part1_with`, and the same error if there is one.
+pub fn calculate_part1_batched(path: &str, alphabet: &Alphabet, mode: Mode) -> Result<usize> {
+    let lut = Lut::new(alphabet)?;
+    let buffer = std::fs::read(path)?;
+    let lines: Vec<(usize, &[u8])> = lines(&buffer).collect();
+    let mut priority_sum = 0;
+    // Like the scalar path, an unmapped item anywhere in the file is reported before any problem in strict mode.
+    let mut first_problem = None;
+
+    let mut masks = [(0u64, 0u64); BATCH_SIZE];
+    for batch in lines.chunks(BATCH_SIZE) {
+        // Encode every compartment in the batch first...
+        for (mask, (_, line)) in masks.iter_mut().zip(batch) {
+            let (comp1, comp2) = line.split_at(line.len()/2);
+            *mask = (lut.encode(comp1), lut.encode(comp2));
+        }
+        // ...then find what's common to each pair.
+        for (&(mask1, mask2), &(line_no, line)) in masks.iter().zip(batch) {
+            if (mask1 | mask2) & INVALID != 0 {
+                unmapped_item(line, alphabet)?;
+            }
+            let common_items = mask1 & mask2;
+            if mode == Mode::Strict && first_problem.is_none() {
+                // Checked in the same order as the scalar path.
+                let problem = shared_item_problem(common_items, line, alphabet)?
+                    .or((line.len() % 2 != 0).then_some(Problem::OddLength(line.len())));
+                first_problem = problem.map(|problem| anyhow!("Line {line_no}: {problem}"));
+            }
+            priority_sum += priority_sum_of(common_items);
+        }
+    }
+    match first_problem {
+        Some(problem) => Err(problem),
+        None => Ok(priority_sum),
+    }
+}
+
+/// Same answer as `calculate_part2_with`, and the same error if there is one.
+pub fn calculate_part2_batched(path: &str, alphabet: &Alphabet, mode: Mode) -> Result<usize> {
+    let lut = Lut::new(alphabet)?;
+    let buffer = std::fs::read(path)?;
+    let lines: Vec<(usize, &[u8])> = lines(&buffer).collect();
+    let mut priority_sum = 0;
+    let mut first_problem = None;
+
+    let mut masks = [0u64; BATCH_SIZE * GROUP_SIZE];
+    for batch in lines.chunks(BATCH_SIZE * GROUP_SIZE) {
+        for (mask, (_, line)) in masks.iter_mut().zip(batch) {
+            *mask = lut.encode(line);
+        }
+        for (group_masks, group) in masks.chunks(GROUP_SIZE).zip(batch.chunks(GROUP_SIZE)) {
+            let group_masks = &group_masks[..group.len()];
+            for (mask, &(_, line)) in group_masks.iter().zip(group) {
+                if mask & INVALID != 0 {
+                    unmapped_item(line, alphabet)?;
+                }
+            }
+            let common_items = group_masks.iter().fold(u64::MAX, |msk1, msk2| msk1 & msk2);
+            if mode == Mode::Strict && first_problem.is_none() {
+                let (line_no, first_rucksack) = group[0];
+                let problem = shared_item_problem(common_items, first_rucksack, alphabet)?
+                    .or((group.len() != GROUP_SIZE).then_some(Problem::IncompleteGroup(group.len())));
+                first_problem = problem.map(|problem| anyhow!("Group starting at line {line_no}: {problem}"));
+            }
+            priority_sum += priority_sum_of(common_items);
+        }
+    }
+    match first_problem {
+        Some(problem) => Err(problem),
+        None => Ok(priority_sum),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    lines: usize,
+    bytes: usize,
+    /// (name, answer, time) for each run.
+    runs: Vec<(&'static str, usize, Duration)>,
+}
+
+/// Time the scalar and batched versions of both parts on the puzzle input repeated `repeats` times.
+pub fn benchmark(repeats: usize) -> Result<BenchReport> {
+    let input = std::fs::read_to_string("input/day3.txt")?;
+    let input = input.trim_end().to_string() + "\n";
+    let path = std::env::temp_dir().join(format!("day3_bench_{}.txt", std::process::id()));
+    std::fs::write(&path, input.repeat(repeats))?;
+    let path_str = path.to_str().ok_or(anyhow!("Temporary path isn't valid UTF-8"))?;
+
+    let alphabet = Alphabet::letters();
+    type Solver = fn(&str, &Alphabet, Mode) -> Result<usize>;
+    let solvers: [(&'static str, Solver); 4] = [
+        ("part 1 scalar", calculate_part1_with),
+        ("part 1 batched", calculate_part1_batched),
+        ("part 2 scalar", calculate_part2_with),
+        ("part 2 batched", calculate_part2_batched),
+    ];
+
+    let mut runs = vec![];
+    for (name, solver) in solvers {
+        let start = Instant::now();
+        let answer = solver(path_str, &alphabet, Mode::Strict);
+        runs.push((name, answer, start.elapsed()));
+    }
+    std::fs::remove_file(&path)?;
+
+    let runs = runs.into_iter()
+        .map(|(name, answer, time)| answer.map(|answer| (name, answer, time)))
+        .collect::<Result<Vec<_>>>()?;
+    for pair in runs.chunks(2) {
+        if pair[0].1 != pair[1].1 {
+            return Err(anyhow!("{} gave {} but {} gave {}!", pair[0].0, pair[0].1, pair[1].0, pair[1].1));
+        }
+    }
+    Ok(BenchReport { lines: input.lines().count() * repeats, bytes: input.len() * repeats, runs })
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} lines, {} bytes", self.lines, self.bytes)?;
+        for (name, answer, time) in &self.runs {
+            let throughput = self.bytes as f64 / time.as_secs_f64() / 1e6;
+            writeln!(f, "  {name:<15} {answer:>12} {:>10.2?} {throughput:>10.1} MB/s", time)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    fn outcome(result: Result<usize>) -> Result<usize, String> {
+        result.map_err(|e| e.to_string())
+    }
+
+    fn both_parts_agree(path: &str, mode: Mode) {
+        let alphabet = Alphabet::letters();
+        assert_eq!(
+            outcome(calculate_part1_with(path, &alphabet, mode)),
+            outcome(calculate_part1_batched(path, &alphabet, mode)),
+        );
+        assert_eq!(
+            outcome(calculate_part2_with(path, &alphabet, mode)),
+            outcome(calculate_part2_batched(path, &alphabet, mode)),
+        );
+    }
+
+    #[test]
+    fn matches_scalar_on_puzzle_input() {
+        both_parts_agree("input/day3.txt", Mode::Strict);
+        both_parts_agree("input/day3.txt", Mode::Lenient);
+    }
+
+    #[test]
+    fn matches_scalar_on_malformed_input() {
+        let alphabet = Alphabet::letters();
+        let input = TempFile::new("day3_batched_malformed", "");
+        let path_str = input.path();
+        for (contents, part1, part2) in [
+            // Multiple shared items, no shared items, an odd length, Windows line endings and an incomplete group.
+            ("abcabcabcdefghiJabcdefghiJ\r\nabcd\n\nxyzzq\nQwQw\n",
+                "Line 1: multiple shared items abcdefg", "Group starting at line 1: no shared item"),
+            // Shared item problems come before an odd length or an incomplete group.
+            ("abc\n", "Line 1: no shared item", "Group starting at line 1: multiple shared items abc"),
+            // An unmapped item is reported even after an earlier problem.
+            ("abcd\naa\nab1b\n", "Item '1' is not in the alphabet!", "Item '1' is not in the alphabet!"),
+        ] {
+            input.write(contents);
+            assert_eq!(outcome(calculate_part1_batched(path_str, &alphabet, Mode::Strict)), Err(part1.to_string()));
+            assert_eq!(outcome(calculate_part2_batched(path_str, &alphabet, Mode::Strict)), Err(part2.to_string()));
+            both_parts_agree(path_str, Mode::Strict);
+            both_parts_agree(path_str, Mode::Lenient);
+        }
+    }
+}
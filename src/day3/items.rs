@@ -0,0 +1,215 @@
+use crate::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/**/
+// Sets of rucksack items over an arbitrary alphabet.
+// Items are stored by priority. Small alphabets use a single u64 bitmask like the original solution,
+// medium ones a wider bitset, and anything bigger falls back to a hash set.
+/**/
+
+/// Largest alphabet (highest priority + 1) that fits in the u64 fast path.
+const SMALL_LIMIT: usize = 64;
+/// Largest alphabet stored as a bitset. Beyond this a hash set is smaller for typical rucksacks.
+const WIDE_LIMIT: usize = 1 << 16;
+
+/// Assigns each item a priority. Items without one are rejected.
+pub struct Alphabet {
+    /// One more than the highest priority `priority` can return.
+    size: usize,
+    priority: Box<dyn Fn(char) -> Option<usize>>,
+}
+
+impl Alphabet {
+    /// `priority` must never return a value >= `size`.
+    pub fn new(size: usize, priority: impl Fn(char) -> Option<usize> + 'static) -> Alphabet {
+        Alphabet { size, priority: Box::new(priority) }
+    }
+
+    /// The puzzle's alphabet: a-z have priorities 1-26, A-Z have 27-52.
+    pub fn letters() -> Alphabet {
+        Alphabet::new(53, super::to_priority)
+    }
+
+    /// The given items have priorities 1, 2, 3... in order.
+    pub fn from_items(items: &str) -> Alphabet {
+        let priorities: HashMap<char, usize> = items.chars()
+            .enumerate()
+            .map(|(n, item)| (item, n + 1))
+            .collect();
+        let size = priorities.values().max().map_or(1, |p| p + 1);
+        Alphabet::new(size, move |item| priorities.get(&item).copied())
+    }
+
+    pub fn priority(&self, item: char) -> Result<usize> {
+        let priority = (self.priority)(item).ok_or(anyhow!("Item '{item}' is not in the alphabet!"))?;
+        if priority >= self.size {
+            return Err(anyhow!("Priority {priority} of '{item}' is outside the alphabet!"));
+        }
+        Ok(priority)
+    }
+
+    pub fn empty_set(&self) -> ItemSet {
+        match self.size {
+            size if size <= SMALL_LIMIT => ItemSet::Small(0),
+            size if size <= WIDE_LIMIT => ItemSet::Wide(vec![0; size.div_ceil(64)]),
+            _ => ItemSet::Sparse(HashSet::new()),
+        }
+    }
+}
+
+/// A set of item priorities.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ItemSet {
+    Small(u64),
+    Wide(Vec<u64>),
+    Sparse(HashSet<usize>),
+}
+
+impl ItemSet {
+    pub fn insert(&mut self, priority: usize) {
+        match self {
+            ItemSet::Small(bitmask) => *bitmask |= 1 << priority,
+            ItemSet::Wide(words) => words[priority / 64] |= 1 << (priority % 64),
+            ItemSet::Sparse(set) => {set.insert(priority);},
+        }
+    }
+
+    pub fn contains(&self, priority: usize) -> bool {
+        match self {
+            ItemSet::Small(bitmask) => priority < 64 && bitmask & (1 << priority) != 0,
+            ItemSet::Wide(words) => words.get(priority / 64).is_some_and(|w| w & (1 << (priority % 64)) != 0),
+            ItemSet::Sparse(set) => set.contains(&priority),
+        }
+    }
+
+    /// Items common to both sets.
+    pub fn intersection(&self, other: &ItemSet) -> ItemSet {
+        match (self, other) {
+            // Items common to both survive a bitwise AND.
+            (ItemSet::Small(a), ItemSet::Small(b)) => ItemSet::Small(a & b),
+            (ItemSet::Wide(a), ItemSet::Wide(b)) => ItemSet::Wide(a.iter().zip(b).map(|(a, b)| a & b).collect()),
+            (ItemSet::Sparse(a), ItemSet::Sparse(b)) => ItemSet::Sparse(a.intersection(b).copied().collect()),
+            // Sets from different alphabets. Keep our representation.
+            _ => {
+                let mut common = self.clone();
+                for priority in self.priorities() {
+                    if !other.contains(priority) {
+                        common.remove(priority);
+                    }
+                }
+                common
+            },
+        }
+    }
+
+    fn remove(&mut self, priority: usize) {
+        match self {
+            ItemSet::Small(bitmask) => *bitmask &= !(1 << priority),
+            ItemSet::Wide(words) => words[priority / 64] &= !(1 << (priority % 64)),
+            ItemSet::Sparse(set) => {set.remove(&priority);},
+        }
+    }
+
+    /// All priorities in the set, in ascending order.
+    pub fn priorities(&self) -> Vec<usize> {
+        match self {
+            ItemSet::Small(bitmask) => bit_positions(*bitmask, 0).collect(),
+            ItemSet::Wide(words) => words.iter()
+                .enumerate()
+                .flat_map(|(n, &word)| bit_positions(word, n * 64))
+                .collect(),
+            ItemSet::Sparse(set) => {
+                let mut priorities: Vec<usize> = set.iter().copied().collect();
+                priorities.sort_unstable();
+                priorities
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ItemSet::Small(bitmask) => bitmask.count_ones() as usize,
+            ItemSet::Wide(words) => words.iter().map(|w| w.count_ones() as usize).sum(),
+            ItemSet::Sparse(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The highest priority in the set.
+    pub fn max_priority(&self) -> Option<usize> {
+        match self {
+            ItemSet::Small(0) => None,
+            ItemSet::Small(bitmask) => Some(bitmask.ilog2() as usize),
+            _ => self.priorities().last().copied(),
+        }
+    }
+}
+
+/// Positions of the set bits in `word`, offset by `base`.
+fn bit_positions(mut word: u64, base: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if word == 0 {
+            return None;
+        }
+        let bit = word.trailing_zeros() as usize;
+        word &= word - 1;
+        Some(base + bit)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An alphabet of `size` priorities where every character is its own code point.
+    fn alphabet(size: usize) -> Alphabet {
+        Alphabet::new(size, |item| Some(item as usize))
+    }
+
+    fn set_of(alphabet: &Alphabet, priorities: &[usize]) -> ItemSet {
+        let mut set = alphabet.empty_set();
+        for &priority in priorities {
+            set.insert(priority);
+        }
+        set
+    }
+
+    #[test]
+    fn picks_representation_by_size() {
+        assert!(matches!(alphabet(64).empty_set(), ItemSet::Small(0)));
+        assert_eq!(alphabet(65).empty_set(), ItemSet::Wide(vec![0; 2]));
+        assert!(matches!(alphabet(1 << 16).empty_set(), ItemSet::Wide(words) if words.len() == 1024));
+        assert!(matches!(alphabet((1 << 16) + 1).empty_set(), ItemSet::Sparse(_)));
+
+        for (size, priorities) in [(64, vec![0, 5, 63]), (65, vec![1, 63, 64]), (1 << 20, vec![2, 70_000, 1_000_000])] {
+            let set = set_of(&alphabet(size), &priorities);
+            assert_eq!(set.priorities(), priorities);
+            assert_eq!(set.len(), 3);
+            assert_eq!(set.max_priority(), priorities.last().copied());
+            assert!(set.contains(priorities[1]) && !set.contains(3));
+        }
+    }
+
+    #[test]
+    fn intersects_mixed_representations() {
+        let small = set_of(&alphabet(64), &[1, 2, 40]);
+        let sparse = set_of(&alphabet(1 << 20), &[2, 40, 100_000]);
+        let wide = set_of(&alphabet(1000), &[1, 40, 999]);
+        // The result keeps the representation of the set it was called on.
+        assert_eq!(small.intersection(&sparse), set_of(&alphabet(64), &[2, 40]));
+        assert_eq!(sparse.intersection(&wide), set_of(&alphabet(1 << 20), &[40]));
+        assert_eq!(wide.intersection(&small), set_of(&alphabet(1000), &[1, 40]));
+    }
+
+    #[test]
+    fn rejects_unmapped_items() {
+        assert_eq!(Alphabet::letters().priority('Z').unwrap(), 52);
+        assert!(Alphabet::letters().priority('1').is_err());
+        assert!(Alphabet::from_items("xyz").priority('a').is_err());
+        // A priority function that breaks its promise is caught too.
+        assert!(Alphabet::new(3, |_| Some(5)).priority('a').is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::prelude::*;
+use std::fmt;
+use std::collections::BTreeMap;
+use super::encode_rucksack;
+use super::items::Alphabet;
+
+/**/
+// Plan how to fix each rucksack so that no item type is in both compartments.
+// Swapping an item from one compartment with an item from the other keeps the compartments the same size,
+// so afterwards each type is entirely on one side. Pick which types stay in the first compartment: their total
+// count has to fill it exactly, and every other type's items in it have to be swapped out. Maximising the items
+// that can stay put is an exact-capacity 0/1 knapsack over item types.
+/**/
+
+/// Exchange the item at position `left.0` of the first compartment with the one at `right.0` of the second.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Swap {
+    pub left: (usize, char),
+    pub right: (usize, char),
+}
+
+/// Find the fewest swaps that leave no item type in both compartments, or None if it's impossible.
+/// Compartments are split the same way as part 1.
+pub fn plan_rucksack(rucksack: &str) -> Option<Vec<Swap>> {
+    let items: Vec<char> = rucksack.chars().collect();
+    let (comp1, comp2) = items.split_at(items.len()/2);
+
+    // (item, count in first compartment, total count), in order of first appearance.
+    let mut types: Vec<(char, usize, usize)> = vec![];
+    for (n, &item) in items.iter().enumerate() {
+        match types.iter_mut().find(|(t, _, _)| *t == item) {
+            Some((_, in_left, total)) => {
+                *in_left += (n < comp1.len()) as usize;
+                *total += 1;
+            },
+            None => types.push((item, (n < comp1.len()) as usize, 1)),
+        }
+    }
+
+    // best[size] is the most first-compartment items that can stay put, using types totalling `size` items.
+    // keep[t][size] records whether type t is kept in the first compartment in that solution.
+    let capacity = comp1.len();
+    let mut best: Vec<Option<usize>> = vec![None; capacity + 1];
+    best[0] = Some(0);
+    let mut keep = vec![vec![false; capacity + 1]; types.len()];
+    for (t, &(_, in_left, total)) in types.iter().enumerate() {
+        for size in (total..=capacity).rev() {
+            let Some(without) = best[size - total] else {continue};
+            if best[size].is_none_or(|b| b < without + in_left) {
+                best[size] = Some(without + in_left);
+                keep[t][size] = true;
+            }
+        }
+    }
+    best[capacity]?;
+
+    // Work out which types stay left by walking back through the table.
+    let mut stays_left = vec![false; types.len()];
+    let mut size = capacity;
+    for t in (0..types.len()).rev() {
+        if keep[t][size] {
+            stays_left[t] = true;
+            size -= types[t].2;
+        }
+    }
+    let goes_left = |item: char| types.iter().zip(&stays_left).any(|((t, _, _), &left)| *t == item && left);
+
+    // Pair every misplaced item in the first compartment with one in the second.
+    let leaving_left = comp1.iter().enumerate().filter(|(_, &item)| !goes_left(item));
+    let leaving_right = comp2.iter().enumerate().filter(|(_, &item)| goes_left(item));
+    Some(leaving_left.zip(leaving_right)
+        .map(|((i, &l), (j, &r))| Swap { left: (i, l), right: (j, r) })
+        .collect())
+}
+
+#[derive(Clone, Debug)]
+pub struct ReorganisationReport {
+    /// (line number, plan) for each rucksack. A plan of None means it can't be done.
+    pub plans: Vec<(usize, Option<Vec<Swap>>)>,
+    /// How many times items of each priority are moved, over every rucksack that can be fixed.
+    pub moves_by_priority: BTreeMap<usize, usize>,
+}
+
+pub fn plan_file(path: &str, alphabet: &Alphabet) -> Result<ReorganisationReport> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut plans = vec![];
+    let mut moves_by_priority = BTreeMap::new();
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {continue}
+        // Reject items outside the alphabet the same way as the other modes, even if they'd stay put.
+        encode_rucksack(&line, alphabet)?;
+
+        let plan = plan_rucksack(&line);
+        for swap in plan.iter().flatten() {
+            for item in [swap.left.1, swap.right.1] {
+                *moves_by_priority.entry(alphabet.priority(item)?).or_insert(0) += 1;
+            }
+        }
+        plans.push((n + 1, plan));
+    }
+    Ok(ReorganisationReport { plans, moves_by_priority })
+}
+
+impl fmt::Display for ReorganisationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (line_no, plan) in &self.plans {
+            match plan {
+                None => writeln!(f, "line {line_no}: impossible")?,
+                Some(swaps) if swaps.is_empty() => writeln!(f, "line {line_no}: already sorted")?,
+                Some(swaps) => {
+                    let swaps: Vec<String> = swaps.iter()
+                        .map(|s| format!("{}@{} <-> {}@{}", s.left.1, s.left.0, s.right.1, s.right.0))
+                        .collect();
+                    writeln!(f, "line {line_no}: {}", swaps.join(", "))?;
+                },
+            }
+        }
+
+        let swaps: usize = self.plans.iter().filter_map(|(_, plan)| plan.as_ref()).map(Vec::len).sum();
+        let impossible = self.plans.iter().filter(|(_, plan)| plan.is_none()).count();
+        writeln!(f)?;
+        writeln!(f, "{} rucksacks, {swaps} swaps, {impossible} impossible", self.plans.len())?;
+        writeln!(f, "Moves by priority:")?;
+        for (priority, moves) in &self.moves_by_priority {
+            writeln!(f, "  {priority:>3}: {moves}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashSet, VecDeque};
+    use crate::day3::{calculate_part1_with, Mode};
+    use crate::testing::TempFile;
+
+    fn apply(rucksack: &str, swaps: &[Swap]) -> Vec<char> {
+        let mut items: Vec<char> = rucksack.chars().collect();
+        let half = items.len() / 2;
+        for swap in swaps {
+            assert_eq!((items[swap.left.0], items[half + swap.right.0]), (swap.left.1, swap.right.1));
+            items.swap(swap.left.0, half + swap.right.0);
+        }
+        items
+    }
+
+    fn sorted(items: &[char]) -> bool {
+        let (comp1, comp2) = items.split_at(items.len()/2);
+        comp1.iter().all(|item| !comp2.contains(item))
+    }
+
+    /// The fewest swaps needed, found by trying every sequence of swaps breadth first.
+    fn fewest_swaps(rucksack: &str) -> Option<usize> {
+        let start: Vec<char> = rucksack.chars().collect();
+        let half = start.len() / 2;
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((items, swaps)) = queue.pop_front() {
+            if sorted(&items) {
+                return Some(swaps);
+            }
+            for i in 0..half {
+                for j in half..items.len() {
+                    let mut next = items.clone();
+                    next.swap(i, j);
+                    if seen.insert(next.clone()) {
+                        queue.push_back((next, swaps + 1));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn plans_the_fewest_swaps() {
+        assert_eq!(plan_rucksack("abcd"), Some(vec![]));
+        assert_eq!(plan_rucksack("abca"), Some(vec![Swap { left: (1, 'b'), right: (1, 'a') }]));
+        for rucksack in ["abca", "aabbabab", "abcdcbaexe", "xyzzyxww"] {
+            let swaps = plan_rucksack(rucksack).unwrap();
+            assert!(sorted(&apply(rucksack, &swaps)), "{rucksack}");
+            assert_eq!(Some(swaps.len()), fewest_swaps(rucksack), "{rucksack}");
+        }
+    }
+
+    #[test]
+    fn reports_impossible_rucksacks() {
+        // Every type has two items, so no set of types fills a compartment of three.
+        assert_eq!(plan_rucksack("abcabc"), None);
+        assert_eq!(fewest_swaps("abcabc"), None);
+        assert_eq!(plan_rucksack("aaab"), None);
+    }
+
+    #[test]
+    fn rejects_unmapped_items() {
+        // The 1s stay put, so only checking the swapped items would miss them.
+        assert!(plan_rucksack("11abca").unwrap().iter().all(|swap| swap.left.1 != '1' && swap.right.1 != '1'));
+        let input = TempFile::new("day3_reorganise", "abca\n11abca\n");
+        let path_str = input.path();
+        let error = plan_file(path_str, &Alphabet::letters()).unwrap_err().to_string();
+        assert_eq!(error, "Item '1' is not in the alphabet!");
+        assert_eq!(error, calculate_part1_with(path_str, &Alphabet::letters(), Mode::Lenient).unwrap_err().to_string());
+    }
+}
@@ -0,0 +1,53 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{Shared, Problem, shared_per_rucksack, shared_per_group};
+use super::items::Alphabet;
+
+/**/
+// Check a rucksack file against the puzzle's assumptions: each rucksack has an even number of items and
+// exactly one item in both compartments, and each group shares exactly one badge.
+/**/
+
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+    pub rucksacks: Vec<(Shared, Vec<Problem>)>,
+    pub groups: Vec<(Shared, Vec<Problem>)>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.rucksacks.iter().chain(&self.groups).all(|(_, problems)| problems.is_empty())
+    }
+}
+
+pub fn validate(path: &str, alphabet: &Alphabet) -> Result<ValidationReport> {
+    Ok(ValidationReport {
+        rucksacks: shared_per_rucksack(path, alphabet)?,
+        groups: shared_per_group(path, alphabet)?,
+    })
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, entries) in [("Rucksack", &self.rucksacks), ("Group", &self.groups)] {
+            let bad: Vec<&(Shared, Vec<Problem>)> = entries.iter().filter(|(_, p)| !p.is_empty()).collect();
+            writeln!(f, "{name}s: {} checked, {} with problems", entries.len(), bad.len())?;
+            for (shared, problems) in bad {
+                let problems: Vec<String> = problems.iter().map(Problem::to_string).collect();
+                writeln!(f, "  {name} at line {}: {}", shared.line_no, problems.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::NoSharedItem => write!(f, "no shared item"),
+            Problem::MultipleSharedItems(items) => write!(f, "multiple shared items {}", items.iter().collect::<String>()),
+            Problem::OddLength(len) => write!(f, "odd number of items ({len})"),
+            Problem::IncompleteGroup(len) => write!(f, "only {len} rucksacks in group"),
+        }
+    }
+}
@@ -1,16 +1,27 @@
 use crate::prelude::*;
+use std::fmt;
 use std::ops::RangeInclusive;
 
+pub mod intervals;
+pub mod roster;
+pub mod reassign;
+use intervals::{Discrete, IntervalSet};
+
 pub fn calculate_part1() -> Result<usize>{
-    let file = File::open("input/day4.txt")?;
+    calculate_part1_with("input/day4.txt", Mode::Strict, parse_number::<u32>)
+}
+
+// Same as part 1, but for any file and any type of section, given a function to parse one.
+pub fn calculate_part1_with<T: PartialOrd + Clone>(path: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<usize>{
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut count: usize = 0;
 
-    for line in reader.lines() {
+    for (n, line) in reader.lines().enumerate() {
         let line = line?;
         if line.is_empty() {continue}
 
-        let (range1, range2) = parse_line(line)?;
+        let (range1, range2) = parse_line_with(&line, mode, &parse).map_err(|e| anyhow!("Line {}: {e}", n + 1))?;
 
         if subset(range1, range2) {
             count += 1;
@@ -19,18 +30,226 @@ pub fn calculate_part1() -> Result<usize>{
     Ok(count)
 }
 
+/// How to treat ranges whose start is after their end, like `7-3`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Mode {
+    /// Reject them.
+    Strict,
+    /// Swap the bounds, so `7-3` means `3-7`.
+    Normalise,
+}
+
+/// Why a line couldn't be parsed.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ParseError {
+    /// There was no comma separating the two ranges.
+    MissingComma,
+    /// There were this many comma-separated ranges instead of two.
+    WrongRangeCount(usize),
+    /// A range had a dash but nothing on one side of it.
+    MissingBound(String),
+    InvalidNumber(String),
+    /// The range could be split at more than one dash, e.g. a date range separated by a dash.
+    Ambiguous(String),
+    /// The range's start is after its end.
+    Reversed(String),
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingComma => write!(f, "expected two ranges separated by a comma"),
+            ParseError::WrongRangeCount(count) => write!(f, "expected two ranges, found {count}"),
+            ParseError::MissingBound(range) => write!(f, "range '{range}' is missing a bound"),
+            ParseError::InvalidNumber(value) => write!(f, "'{value}' is not a valid section"),
+            ParseError::Ambiguous(range) => write!(f, "range '{range}' can be split at more than one dash"),
+            ParseError::Reversed(range) => write!(f, "range '{range}' starts after it ends"),
+        }
+    }
+}
+impl std::error::Error for ParseError {}
+
 //Assumes input is of the form X-Y,Z-W. Returns the ranges X..=Y and Z..=W.
-fn parse_line(line: String) -> Result<(RangeInclusive<u32>, RangeInclusive<u32>)> {
-    let mut ranges: Vec<RangeInclusive<u32>> = vec![];
+// A single number X is shorthand for X-X.
+fn parse_line(line: &str, mode: Mode) -> Result<(RangeInclusive<u32>, RangeInclusive<u32>), ParseError> {
+    parse_line_with(line, mode, parse_number::<u32>)
+}
+
+/// Same as `parse_line`, but for any type of section, given a function to parse one.
+pub fn parse_line_with<T: PartialOrd + Clone>(line: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<(RangeInclusive<T>, RangeInclusive<T>), ParseError> {
     let range_strings: Vec<&str> = line.split(',').collect();
-    
-    for range_str in [range_strings[0], range_strings[1]] {
-        let values: Vec<&str> = range_str.split('-').collect();
-        let start: u32 = values[0].parse()?;
-        let end:   u32 = values[1].parse()?;
-        ranges.push(start..=end);
+    match range_strings.len() {
+        1 => return Err(ParseError::MissingComma),
+        2 => (),
+        count => return Err(ParseError::WrongRangeCount(count)),
+    }
+    Ok((parse_range(range_strings[0], mode, &parse)?, parse_range(range_strings[1], mode, &parse)?))
+}
+
+// Sections may contain dashes themselves (negative numbers, dates), so try splitting at every dash and keep
+// whichever split gives two valid sections.
+fn parse_range<T: PartialOrd + Clone>(range_str: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<RangeInclusive<T>, ParseError> {
+    let range_str = range_str.trim();
+    let mut splits = range_str.match_indices('-')
+        .map(|(i, _)| (range_str[..i].trim(), range_str[i+1..].trim()))
+        .filter_map(|(start, end)| Some((parse(start)?, parse(end)?)));
+
+    let (start, end) = match (splits.next(), splits.next()) {
+        (Some(_), Some(_)) => return Err(ParseError::Ambiguous(range_str.to_string())),
+        (Some(bounds), None) => bounds,
+        (None, _) => {
+            if let Some(section) = parse(range_str) {
+                // Single-section shorthand.
+                (section.clone(), section)
+            } else if range_str.starts_with('-') || range_str.ends_with('-') {
+                return Err(ParseError::MissingBound(range_str.to_string()));
+            } else {
+                let value = range_str.split('-').find(|v| parse(v.trim()).is_none()).unwrap_or(range_str);
+                return Err(ParseError::InvalidNumber(value.trim().to_string()));
+            }
+        },
+    };
+
+    match (start <= end, mode) {
+        (true, _) => Ok(start..=end),
+        (false, Mode::Normalise) => Ok(end..=start),
+        (false, Mode::Strict) => Err(ParseError::Reversed(range_str.to_string())),
+    }
+}
+
+/// Parse any integer type.
+pub fn parse_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    value.parse().ok()
+}
+
+/// A date as (year, month, day), which sorts chronologically.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub struct Date(pub i32, pub u8, pub u8);
+
+/// A timestamp to the minute as (year, month, day, hour, minute), which sorts chronologically.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub struct DateTime(pub i32, pub u8, pub u8, pub u8, pub u8);
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    let leap = year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    /// Days since 1970-01-01, counting back through the proleptic Gregorian calendar for earlier dates.
+    fn days(self) -> i64 {
+        let Date(year, month, day) = self;
+        // Count years from March, so the leap day is at the end of the year.
+        let year = year as i64 - (month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+}
+
+impl Discrete for Date {
+    fn succ(self) -> Option<Self> {
+        let Date(year, month, day) = self;
+        Some(match (day < days_in_month(year, month), month < 12) {
+            (true, _) => Date(year, month, day + 1),
+            (false, true) => Date(year, month + 1, 1),
+            (false, false) => Date(year.checked_add(1)?, 1, 1),
+        })
+    }
+    fn pred(self) -> Option<Self> {
+        let Date(year, month, day) = self;
+        Some(match (day > 1, month > 1) {
+            (true, _) => Date(year, month, day - 1),
+            (false, true) => Date(year, month - 1, days_in_month(year, month - 1)),
+            (false, false) => Date(year.checked_sub(1)?, 12, 31),
+        })
+    }
+    fn count(start: Self, end: Self) -> u128 {
+        if start > end {return 0}
+        (end.days() - start.days()) as u128 + 1
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.0, self.1, self.2)
     }
-    Ok((ranges[0].clone(), ranges[1].clone()))
+}
+
+impl DateTime {
+    fn date(self) -> Date {
+        Date(self.0, self.1, self.2)
+    }
+
+    fn minutes(self) -> i64 {
+        self.date().days() * 24 * 60 + self.3 as i64 * 60 + self.4 as i64
+    }
+}
+
+impl Discrete for DateTime {
+    fn succ(self) -> Option<Self> {
+        let DateTime(year, month, day, hour, minute) = self;
+        Some(match (minute < 59, hour < 23) {
+            (true, _) => DateTime(year, month, day, hour, minute + 1),
+            (false, true) => DateTime(year, month, day, hour + 1, 0),
+            (false, false) => {
+                let Date(year, month, day) = self.date().succ()?;
+                DateTime(year, month, day, 0, 0)
+            },
+        })
+    }
+    fn pred(self) -> Option<Self> {
+        let DateTime(year, month, day, hour, minute) = self;
+        Some(match (minute > 0, hour > 0) {
+            (true, _) => DateTime(year, month, day, hour, minute - 1),
+            (false, true) => DateTime(year, month, day, hour - 1, 59),
+            (false, false) => {
+                let Date(year, month, day) = self.date().pred()?;
+                DateTime(year, month, day, 23, 59)
+            },
+        })
+    }
+    fn count(start: Self, end: Self) -> u128 {
+        if start > end {return 0}
+        (end.minutes() - start.minutes()) as u128 + 1
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}T{:02}:{:02}", self.date(), self.3, self.4)
+    }
+}
+
+/// Parse a date like 2024-01-31.
+pub fn parse_date(value: &str) -> Option<Date> {
+    let mut parts = value.split('-');
+    let year: i32 = parts.next()?.parse().ok()?;
+    let month: u8 = parts.next()?.parse().ok()?;
+    let day: u8 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    Some(Date(year, month, day))
+}
+
+/// Parse a timestamp like 2024-01-31T09:30.
+pub fn parse_datetime(value: &str) -> Option<DateTime> {
+    let (date, time) = value.split_once('T')?;
+    let Date(year, month, day) = parse_date(date)?;
+    let (hour, minute) = time.split_once(':')?;
+    let hour: u8 = hour.parse().ok()?;
+    let minute: u8 = minute.parse().ok()?;
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+    Some(DateTime(year, month, day, hour, minute))
 }
 
 /// Returns true if r1 is completely contained inside r2, or vice versa.
@@ -43,15 +262,19 @@ fn subset<T: PartialOrd>(r1: RangeInclusive<T>, r2: RangeInclusive<T>) -> bool {
 
 // Almost identical to above, but uses overlap instead of subset.
 pub fn calculate_part2() -> Result<usize>{
-    let file = File::open("input/day4.txt")?;
+    calculate_part2_with("input/day4.txt", Mode::Strict, parse_number::<u32>)
+}
+
+pub fn calculate_part2_with<T: PartialOrd + Clone>(path: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<usize>{
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut count: usize = 0;
 
-    for line in reader.lines() {
+    for (n, line) in reader.lines().enumerate() {
         let line = line?;
         if line.is_empty() {continue}
 
-        let (range1, range2) = parse_line(line)?;
+        let (range1, range2) = parse_line_with(&line, mode, &parse).map_err(|e| anyhow!("Line {}: {e}", n + 1))?;
 
         if overlap(range1, range2) {
             count += 1;
@@ -64,3 +287,140 @@ fn overlap<T: PartialOrd>(r1: RangeInclusive<T>, r2: RangeInclusive<T>) -> bool
     (r1.contains(r2.start()) || r1.contains(r2.end())) ||
     (r2.contains(r1.start()) || r2.contains(r1.end()))
 }
+
+
+/* Coverage */
+
+/// How the sections between the lowest and highest assigned are covered across every elf.
+#[derive(Clone, Debug)]
+pub struct Coverage<T> {
+    /// Sections assigned to at least one elf.
+    pub covered: IntervalSet<T>,
+    /// Sections between the lowest and highest assigned that nobody has.
+    pub uncovered: IntervalSet<T>,
+    /// Sections assigned to two or more elves.
+    pub covered_twice: IntervalSet<T>,
+}
+
+pub fn coverage<T: Discrete>(path: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<Coverage<T>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut covered = IntervalSet::new();
+    let mut covered_twice = IntervalSet::new();
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {continue}
+
+        let (range1, range2) = parse_line_with(&line, mode, &parse).map_err(|e| anyhow!("Line {}: {e}", n + 1))?;
+        for range in [range1, range2] {
+            // Anything already covered that this range covers again is now covered twice.
+            let range: IntervalSet<T> = [range].into_iter().collect();
+            covered_twice.merge(&covered.intersection(&range));
+            covered.merge(&range);
+        }
+    }
+
+    let span: IntervalSet<T> = match (covered.ranges().first(), covered.ranges().last()) {
+        (Some(first), Some(last)) => [*first.start()..=*last.end()].into_iter().collect(),
+        _ => IntervalSet::new(),
+    };
+    let uncovered = span.difference(&covered);
+    Ok(Coverage { covered, uncovered, covered_twice })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    #[test]
+    fn parses_integer_extremes() {
+        let line = format!("0-{max},{max}-{max}", max = u64::MAX);
+        let (r1, r2) = parse_line_with(&line, Mode::Strict, parse_number::<u64>).unwrap();
+        assert_eq!(r1, 0..=u64::MAX);
+        assert_eq!(r2, u64::MAX..=u64::MAX);
+        assert!(subset(r1.clone(), r2.clone()));
+        assert!(overlap(r1, r2));
+
+        let line = format!("{max},0-{}", u128::MAX - 1, max = u128::MAX);
+        let (r1, r2) = parse_line_with(&line, Mode::Strict, parse_number::<u128>).unwrap();
+        assert!(!overlap(r1, r2));
+
+        let too_big = format!("0-{},1-2", u64::MAX as u128 + 1);
+        assert_eq!(parse_line_with(&too_big, Mode::Strict, parse_number::<u64>),
+            Err(ParseError::InvalidNumber((u64::MAX as u128 + 1).to_string())));
+        assert_eq!(parse_line_with("0-256,1-2", Mode::Strict, parse_number::<u8>),
+            Err(ParseError::InvalidNumber("256".to_string())));
+    }
+
+    #[test]
+    fn parses_signed_ranges() {
+        let (r1, r2) = parse_line_with("-5--3,-4-2", Mode::Strict, parse_number::<i8>).unwrap();
+        assert_eq!((r1.clone(), r2.clone()), (-5..=-3, -4..=2));
+        assert!(overlap(r1, r2));
+
+        let (r1, r2) = parse_line_with(&format!("{min}-{max},-1", min = i64::MIN, max = i64::MAX), Mode::Strict, parse_number::<i64>).unwrap();
+        assert_eq!((r1, r2), (i64::MIN..=i64::MAX, -1..=-1));
+
+        assert_eq!(parse_line_with("3--3,1-2", Mode::Strict, parse_number::<i32>), Err(ParseError::Reversed("3--3".to_string())));
+        assert_eq!(parse_line_with("3--3,1-2", Mode::Normalise, parse_number::<i32>).unwrap().0, -3..=3);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(parse_line("1-2", Mode::Strict), Err(ParseError::MissingComma));
+        assert_eq!(parse_line("1-2,3-4,5-6", Mode::Strict), Err(ParseError::WrongRangeCount(3)));
+        assert_eq!(parse_line("3-,1-2", Mode::Strict), Err(ParseError::MissingBound("3-".to_string())));
+        assert_eq!(parse_line("a-3,1-2", Mode::Strict), Err(ParseError::InvalidNumber("a".to_string())));
+        assert_eq!(parse_line("7-3,1-2", Mode::Strict), Err(ParseError::Reversed("7-3".to_string())));
+        assert_eq!(parse_line("7-3,5", Mode::Normalise), Ok((3..=7, 5..=5)));
+    }
+
+    #[test]
+    fn parses_date_and_time_ranges() {
+        let line = "2024-01-05-2024-01-09,2024-01-09";
+        let (r1, r2) = parse_line_with(line, Mode::Strict, parse_date).unwrap();
+        assert_eq!(r1, Date(2024, 1, 5)..=Date(2024, 1, 9));
+        assert!(subset(r1, r2));
+
+        let line = "2024-03-01T09:00-2024-03-01T17:00,2024-03-01T17:30-2024-03-02T09:00";
+        let (r1, r2) = parse_line_with(line, Mode::Strict, parse_datetime).unwrap();
+        assert!(!overlap(r1, r2));
+    }
+
+    #[test]
+    fn steps_through_dates_and_times() {
+        assert_eq!(Date(1970, 1, 1).days(), 0);
+        assert_eq!(Date(2023, 12, 31).succ(), Some(Date(2024, 1, 1)));
+        assert_eq!(Date(2024, 3, 1).pred(), Some(Date(2024, 2, 29)));
+        assert_eq!(Date(2023, 3, 1).pred(), Some(Date(2023, 2, 28)));
+        assert_eq!(Date(i32::MAX, 12, 31).succ(), None);
+        assert_eq!(Date::count(Date(2000, 1, 1), Date(2000, 12, 31)), 366);
+        assert_eq!(Date::count(Date(1900, 1, 1), Date(1900, 12, 31)), 365);
+        assert_eq!(parse_date("2023-02-29"), None);
+
+        assert_eq!(DateTime(2024, 1, 31, 23, 59).succ(), Some(DateTime(2024, 2, 1, 0, 0)));
+        assert_eq!(DateTime(2024, 1, 1, 0, 0).pred(), Some(DateTime(2023, 12, 31, 23, 59)));
+        assert_eq!(DateTime::count(DateTime(2024, 2, 28, 12, 0), DateTime(2024, 3, 1, 11, 59)), 2 * 24 * 60);
+        assert_eq!(DateTime(2024, 1, 5, 9, 3).to_string(), "2024-01-05T09:03");
+    }
+
+    #[test]
+    fn coverage_reaches_the_extremes() {
+        let line = format!("{min}-{},{}-{max}\n", i64::MIN + 1, i64::MAX - 1, min = i64::MIN, max = i64::MAX);
+        let input = TempFile::new("day4_coverage", &line);
+        let path_str = input.path();
+        let signed = coverage(path_str, Mode::Strict, parse_number::<i64>).unwrap();
+        assert_eq!(signed.covered.ranges(), [i64::MIN..=i64::MIN + 1, i64::MAX - 1..=i64::MAX]);
+        assert_eq!(signed.uncovered.ranges(), [i64::MIN + 2..=i64::MAX - 2]);
+        assert_eq!(signed.uncovered.len(), (1 << 64) - 4);
+        assert!(signed.covered_twice.is_empty());
+
+        input.write(&format!("0-{max},{max}\n", max = u64::MAX));
+        let unsigned = coverage(path_str, Mode::Strict, parse_number::<u64>).unwrap();
+        assert_eq!(unsigned.covered.ranges(), [0..=u64::MAX]);
+        assert_eq!(unsigned.covered_twice.ranges(), [u64::MAX..=u64::MAX]);
+        assert!(unsigned.uncovered.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use std::ops::RangeInclusive;
+
+/**/
+// A set of sections stored as sorted, disjoint, inclusive ranges.
+// Sections are discrete, so ranges that touch (e.g. 1-3 and 4-6) are merged as well as ones that overlap.
+/**/
+
+/// A type whose values can be stepped through one at a time.
+pub trait Discrete: Ord + Copy {
+    fn succ(self) -> Option<Self>;
+    fn pred(self) -> Option<Self>;
+    /// Number of values in start..=end. Saturates at u128::MAX.
+    fn count(start: Self, end: Self) -> u128;
+}
+
+macro_rules! impl_discrete {
+    ($($t:ty => $unsigned:ty),*) => {$(
+        impl Discrete for $t {
+            fn succ(self) -> Option<Self> { self.checked_add(1) }
+            fn pred(self) -> Option<Self> { self.checked_sub(1) }
+            fn count(start: Self, end: Self) -> u128 {
+                if start > end {return 0}
+                // The difference always fits in the unsigned type of the same width.
+                (end.wrapping_sub(start) as $unsigned as u128).saturating_add(1)
+            }
+        }
+    )*};
+}
+impl_discrete!(u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize,
+               i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct IntervalSet<T> {
+    /// Sorted, and no two ranges overlap or touch.
+    ranges: Vec<RangeInclusive<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { ranges: vec![] }
+    }
+}
+
+impl<T: Discrete> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+/// True if `end` is immediately followed by, or overlaps, a range starting at `start`.
+fn touches<T: Discrete>(end: T, start: T) -> bool {
+    start <= end || end.succ() == Some(start)
+}
+
+impl<T: Discrete> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet::default()
+    }
+
+    pub fn ranges(&self) -> &[RangeInclusive<T>] {
+        &self.ranges
+    }
+
+    /// Add a range, merging it with any it overlaps or touches. Empty ranges are ignored.
+    pub fn insert(&mut self, range: RangeInclusive<T>) {
+        let (mut start, mut end) = (*range.start(), *range.end());
+        if start > end {return}
+
+        // Ranges before `first` end too early to merge, and ranges from `last` on start too late.
+        let first = self.ranges.partition_point(|r| !touches(*r.end(), start));
+        let last = self.ranges.partition_point(|r| touches(end, *r.start()));
+        if first < last {
+            start = start.min(*self.ranges[first].start());
+            end = end.max(*self.ranges[last - 1].end());
+        }
+        self.ranges.splice(first..last, [start..=end]);
+    }
+
+    /// Add every range of `other` to this set.
+    pub fn merge(&mut self, other: &IntervalSet<T>) {
+        for range in &other.ranges {
+            self.insert(range.clone());
+        }
+    }
+
+    pub fn contains(&self, point: &T) -> bool {
+        let index = self.ranges.partition_point(|r| r.end() < point);
+        self.ranges.get(index).is_some_and(|r| r.contains(point))
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut union = self.clone();
+        union.merge(other);
+        union
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = *a.start().max(b.start());
+            let end = *a.end().min(b.end());
+            if start <= end {
+                ranges.push(start..=end);
+            }
+            // Whichever range ends first can't overlap anything else.
+            if a.end() < b.end() { i += 1 } else { j += 1 }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Sections in this set but not in `other`.
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges = vec![];
+        let mut j = 0;
+        for range in &self.ranges {
+            let mut start = Some(*range.start());
+            // Skip ranges of `other` that end before this one.
+            while j < other.ranges.len() && other.ranges[j].end() < range.start() {
+                j += 1;
+            }
+            let mut k = j;
+            while let (Some(s), Some(cut)) = (start, other.ranges.get(k)) {
+                if cut.start() > range.end() {break}
+                if *cut.start() > s {
+                    ranges.push(s..=cut.start().pred().expect("cut.start() > s, so it has a predecessor"));
+                }
+                start = cut.end().succ();
+                k += 1;
+            }
+            if let Some(s) = start.filter(|s| s <= range.end()) {
+                ranges.push(s..=*range.end());
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Total number of sections covered. Saturates at u128::MAX.
+    pub fn len(&self) -> u128 {
+        self.ranges.iter()
+            .map(|r| T::count(*r.start(), *r.end()))
+            .fold(0, u128::saturating_add)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set<T: Discrete>(ranges: impl IntoIterator<Item = RangeInclusive<T>>) -> IntervalSet<T> {
+        ranges.into_iter().collect()
+    }
+
+    #[test]
+    fn insert_merges_touching_and_overlapping_ranges() {
+        let mut sections = set([1..=3, 10..=12]);
+        sections.insert(4..=5);
+        assert_eq!(sections.ranges(), [1..=5, 10..=12]);
+        sections.insert(7..=8);
+        assert_eq!(sections.ranges(), [1..=5, 7..=8, 10..=12]);
+        sections.insert(3..=11);
+        assert_eq!(sections.ranges(), [1..=12]);
+        #[allow(clippy::reversed_empty_ranges)]
+        sections.insert(20..=15);
+        assert_eq!(sections.ranges(), [1..=12]);
+    }
+
+    #[test]
+    fn difference_cuts_both_ends_and_the_middle() {
+        let sections = set([1..=10u32, 20..=30]);
+        let cuts = set([0..=2, 5..=6, 9..=21, 30..=40]);
+        assert_eq!(sections.difference(&cuts).ranges(), [3..=4, 7..=8, 22..=29]);
+        assert!(sections.difference(&sections).is_empty());
+        assert_eq!(sections.difference(&IntervalSet::new()), sections);
+    }
+
+    #[test]
+    fn intersection_keeps_shared_ends() {
+        let a = set([1..=5, 10..=15]);
+        let b = set([5..=10, 15..=20]);
+        assert_eq!(a.intersection(&b).ranges(), [5..=5, 10..=10, 15..=15]);
+        assert_eq!(a.union(&b).ranges(), [1..=20]);
+    }
+
+    #[test]
+    fn contains_only_covered_points() {
+        let sections = set([1..=3, 7..=9]);
+        assert!([1, 2, 3, 7, 9].iter().all(|p| sections.contains(p)));
+        assert!(![0, 4, 6, 10].iter().any(|p| sections.contains(p)));
+        assert!(!IntervalSet::<u32>::new().contains(&0));
+    }
+
+    #[test]
+    fn handles_extreme_bounds() {
+        let signed = set([i64::MIN..=i64::MIN + 1, i64::MAX..=i64::MAX]);
+        assert_eq!(signed.len(), 3);
+        assert_eq!(set([i64::MIN..=i64::MAX]).len(), 1 << 64);
+        assert_eq!(set([i64::MIN..=-1]).difference(&set([i64::MIN..=i64::MIN])).ranges(), [i64::MIN + 1..=-1]);
+
+        let unsigned = set([u64::MAX - 1..=u64::MAX]);
+        assert_eq!(unsigned.union(&set([0..=u64::MAX - 2])).ranges(), [0..=u64::MAX]);
+        assert_eq!(set([0..=u64::MAX]).difference(&unsigned).ranges(), [0..=u64::MAX - 2]);
+
+        // Every u128 is 2^128 sections, one more than fits, so the count saturates.
+        assert_eq!(set([0..=u128::MAX]).len(), u128::MAX);
+        assert_eq!(set([i128::MIN..=i128::MAX]).len(), u128::MAX);
+    }
+}
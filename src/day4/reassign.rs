@@ -0,0 +1,187 @@
+use crate::prelude::*;
+use std::fmt;
+use std::ops::RangeInclusive;
+use super::{Mode, parse_line_with, overlap};
+use super::intervals::Discrete;
+
+/**/
+// Change each pair's assignments as little as possible so the two elves no longer overlap, while still covering
+// every section either of them originally had.
+// If the two ranges overlap, their union is one contiguous block, so the only options are to split that block at
+// some section k and give one side to each elf. The costs only change slope at the original bounds, so it's
+// enough to try splitting at those (and at the ends of the block) rather than at every section.
+/**/
+
+/// What to minimise.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Objective {
+    /// Sections taken away from an elf that originally had them.
+    FewestSectionsDropped,
+    /// Elves whose assignment changes at all.
+    FewestElvesReassigned,
+}
+
+/// The result for one line of the input.
+#[derive(Clone, Debug)]
+pub struct Reassignment<T> {
+    pub line_no: usize,
+    pub original: (RangeInclusive<T>, RangeInclusive<T>),
+    /// None if the pair can't be made disjoint, because they both only cover the same single section.
+    pub new: Option<(RangeInclusive<T>, RangeInclusive<T>)>,
+}
+
+fn len<T: Discrete>(range: &RangeInclusive<T>) -> u128 {
+    T::count(*range.start(), *range.end())
+}
+
+fn common<T: Discrete>(r1: &RangeInclusive<T>, r2: &RangeInclusive<T>) -> u128 {
+    len(&(*r1.start().max(r2.start())..=*r1.end().min(r2.end())))
+}
+
+/// (sections dropped, elves reassigned, sections added) going from `original` to `new`.
+fn cost<T: Discrete>(original: &(RangeInclusive<T>, RangeInclusive<T>), new: &(RangeInclusive<T>, RangeInclusive<T>)) -> (u128, usize, u128) {
+    let mut dropped = 0;
+    let mut reassigned = 0;
+    let mut added = 0;
+    for (old, new) in [(&original.0, &new.0), (&original.1, &new.1)] {
+        let kept = common(old, new);
+        dropped += len(old) - kept;
+        added += len(new) - kept;
+        reassigned += (old != new) as usize;
+    }
+    (dropped, reassigned, added)
+}
+
+/// Make one pair disjoint with the smallest change.
+pub fn reassign_pair<T: Discrete>(r1: RangeInclusive<T>, r2: RangeInclusive<T>, objective: Objective) -> Option<(RangeInclusive<T>, RangeInclusive<T>)> {
+    if !overlap(r1.clone(), r2.clone()) {
+        return Some((r1, r2));
+    }
+    let low = *r1.start().min(r2.start());
+    let high = *r1.end().max(r2.end());
+    if low == high {
+        return None;
+    }
+
+    // Split points k, where one elf gets low..=k and the other k+1..=high.
+    let candidates = [Some(low), high.pred(), Some(*r1.end()), Some(*r2.end()), r1.start().pred(), r2.start().pred()];
+    let original = (r1, r2);
+    candidates.into_iter()
+        .flatten()
+        .filter(|k| (low..high).contains(k))
+        .flat_map(|k| {
+            let next = k.succ().expect("k < high, so it has a successor");
+            [(low..=k, next..=high), (next..=high, low..=k)]
+        })
+        .min_by_key(|new| {
+            let (dropped, reassigned, added) = cost(&original, new);
+            match objective {
+                Objective::FewestSectionsDropped => (dropped, reassigned as u128, added),
+                Objective::FewestElvesReassigned => (reassigned as u128, dropped, added),
+            }
+        })
+}
+
+#[derive(Clone, Debug)]
+pub struct ReassignmentReport<T> {
+    pub lines: Vec<Reassignment<T>>,
+}
+
+pub fn reassign_file<T: Discrete>(path: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>, objective: Objective) -> Result<ReassignmentReport<T>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut lines = vec![];
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {continue}
+
+        let (range1, range2) = parse_line_with(&line, mode, &parse).map_err(|e| anyhow!("Line {}: {e}", n + 1))?;
+        let new = reassign_pair(range1.clone(), range2.clone(), objective);
+        lines.push(Reassignment { line_no: n + 1, original: (range1, range2), new });
+    }
+    Ok(ReassignmentReport { lines })
+}
+
+impl<T: Discrete + fmt::Display> ReassignmentReport<T> {
+    /// The new assignments in the puzzle's `X-Y,Z-W` format, ready to feed back into `calculate_part2`.
+    /// Fails if any pair couldn't be made disjoint.
+    pub fn to_input(&self) -> Result<String> {
+        let mut output = String::new();
+        for line in &self.lines {
+            let (r1, r2) = line.new.as_ref()
+                .ok_or(anyhow!("Line {}: both elves only cover the same single section", line.line_no))?;
+            output += &format!("{}-{},{}-{}\n", r1.start(), r1.end(), r2.start(), r2.end());
+        }
+        Ok(output)
+    }
+}
+
+impl<T: Discrete> fmt::Display for ReassignmentReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut totals = (0, 0, 0);
+        let mut changed_pairs = 0;
+        let mut impossible = vec![];
+        for line in &self.lines {
+            let Some(new) = &line.new else {
+                impossible.push(line.line_no.to_string());
+                continue;
+            };
+            let (dropped, reassigned, added) = cost(&line.original, new);
+            totals = (totals.0 + dropped, totals.1 + reassigned, totals.2 + added);
+            changed_pairs += (reassigned > 0) as usize;
+        }
+        writeln!(f, "Pairs changed: {changed_pairs} of {}", self.lines.len())?;
+        writeln!(f, "Elves reassigned: {}", totals.1)?;
+        writeln!(f, "Sections dropped: {}", totals.0)?;
+        writeln!(f, "Sections added: {}", totals.2)?;
+        if !impossible.is_empty() {
+            writeln!(f, "Impossible (same single section): lines {}", impossible.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day4::{calculate_part2_with, parse_number, parse_date};
+    use crate::testing::TempFile;
+
+    #[test]
+    fn reassigned_input_has_no_overlaps() {
+        for objective in [Objective::FewestSectionsDropped, Objective::FewestElvesReassigned] {
+            let report = reassign_file("input/day4.txt", Mode::Strict, parse_number::<u32>, objective).unwrap();
+            let input = TempFile::new(&format!("day4_reassigned_{objective:?}"), &report.to_input().unwrap());
+            assert_eq!(calculate_part2_with(input.path(), Mode::Strict, parse_number::<u32>).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn same_single_section_is_impossible() {
+        for objective in [Objective::FewestSectionsDropped, Objective::FewestElvesReassigned] {
+            assert_eq!(reassign_pair(5..=5, 5..=5, objective), None);
+            assert_eq!(reassign_pair(5..=6, 5..=5, objective).map(|(r1, r2)| len(&r1) + len(&r2)), Some(2));
+        }
+    }
+
+    #[test]
+    fn handles_64_bit_extremes() {
+        let objective = Objective::FewestSectionsDropped;
+        assert_eq!(reassign_pair(0..=u64::MAX, u64::MAX..=u64::MAX, objective), Some((0..=u64::MAX - 1, u64::MAX..=u64::MAX)));
+        assert_eq!(reassign_pair(i64::MIN..=i64::MIN + 1, i64::MIN..=i64::MIN, objective),
+            Some((i64::MIN + 1..=i64::MIN + 1, i64::MIN..=i64::MIN)));
+        assert_eq!(len(&(i64::MIN..=i64::MAX)), 1 << 64);
+    }
+
+    #[test]
+    fn reassigns_dates() {
+        let input = TempFile::new("day4_reassign_dates", "2024-02-28-2024-03-02,2024-03-01-2024-03-05\n");
+        let report = reassign_file(input.path(), Mode::Strict, parse_date, Objective::FewestSectionsDropped).unwrap();
+        // Across the leap day, as the split after 2 March drops the same two days as the split after 29 February.
+        let output = report.to_input().unwrap();
+        assert_eq!(output, "2024-02-28-2024-03-02,2024-03-03-2024-03-05\n");
+        input.write(&output);
+        assert_eq!(calculate_part2_with(input.path(), Mode::Strict, parse_date).unwrap(), 0);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::prelude::*;
+use std::fmt;
+use std::ops::RangeInclusive;
+use super::{Mode, parse_line_with, subset};
+use super::intervals::{Discrete, IntervalSet};
+
+/**/
+// Compare every assignment in the file with every other, not just the two on the same line.
+// Sweep through the assignments in order of start section, keeping a list of those still active (ending at or
+// after the current start). Everything still active overlaps the new assignment, so the work done is
+// proportional to the number of overlapping pairs rather than n².
+/**/
+
+/// One elf's assignment: the line it came from (starting at 1), which elf on that line (1 or 2), and its sections.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Assignment<T> {
+    pub line_no: usize,
+    pub elf: usize,
+    pub sections: RangeInclusive<T>,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Relation {
+    /// One assignment contains the other.
+    Contains,
+    /// They share some sections, but neither contains the other.
+    Overlaps,
+}
+
+#[derive(Clone, Debug)]
+pub struct RosterReport<T> {
+    pub assignments: Vec<Assignment<T>>,
+    /// Indices into `assignments` of every pair that overlaps.
+    pub pairs: Vec<(usize, usize, Relation)>,
+    /// The most assignments covering any one section.
+    pub max_overlap: usize,
+    /// The sections covered by `max_overlap` assignments.
+    pub busiest_sections: IntervalSet<T>,
+}
+
+pub fn read_assignments<T: Discrete>(path: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<Assignment<T>>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut assignments = vec![];
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {continue}
+
+        let (range1, range2) = parse_line_with(&line, mode, &parse).map_err(|e| anyhow!("Line {}: {e}", n + 1))?;
+        assignments.push(Assignment { line_no: n + 1, elf: 1, sections: range1 });
+        assignments.push(Assignment { line_no: n + 1, elf: 2, sections: range2 });
+    }
+    Ok(assignments)
+}
+
+/// Every pair of assignments that overlap, as indices into `assignments`.
+pub fn overlapping_pairs<T: Discrete>(assignments: &[Assignment<T>]) -> Vec<(usize, usize, Relation)> {
+    let mut order: Vec<usize> = (0..assignments.len()).collect();
+    order.sort_by_key(|&i| *assignments[i].sections.start());
+
+    let mut pairs = vec![];
+    let mut active: Vec<usize> = vec![];
+    for i in order {
+        let sections = &assignments[i].sections;
+        // Anything ending before this starts can't overlap this or anything after it.
+        active.retain(|&j| assignments[j].sections.end() >= sections.start());
+        for &j in &active {
+            let relation = if subset(assignments[j].sections.clone(), sections.clone()) {
+                Relation::Contains
+            } else {
+                Relation::Overlaps
+            };
+            pairs.push((j.min(i), j.max(i), relation));
+        }
+        active.push(i);
+    }
+    pairs.sort_unstable_by_key(|&(i, j, _)| (i, j));
+    pairs
+}
+
+/// The most assignments covering a single section, and which sections they are.
+pub fn busiest_sections<T: Discrete>(assignments: &[Assignment<T>]) -> (usize, IntervalSet<T>) {
+    // +1 where each assignment starts, -1 just after it ends. An end is (section, true), meaning "after section",
+    // so there's no need to step past the last section there is.
+    let mut events: Vec<((T, bool), i64)> = assignments.iter()
+        .flat_map(|a| [((*a.sections.start(), false), 1), ((*a.sections.end(), true), -1)])
+        .collect();
+    events.sort_unstable();
+
+    let mut max_overlap = 0;
+    let mut busiest = IntervalSet::new();
+    let mut current: i64 = 0;
+    for (n, &((position, after), change)) in events.iter().enumerate() {
+        current += change;
+        // Coverage is constant from here until the next event.
+        let Some(&((next, next_after), _)) = events.get(n + 1) else {break};
+        let first = if after {position.succ()} else {Some(position)};
+        let last = if next_after {Some(next)} else {next.pred()};
+        let (Some(first), Some(last)) = (first, last) else {continue};
+        if first > last {continue}
+        let segment = first..=last;
+        match (current as usize).cmp(&max_overlap) {
+            std::cmp::Ordering::Greater => {
+                max_overlap = current as usize;
+                busiest = [segment].into_iter().collect();
+            },
+            std::cmp::Ordering::Equal if max_overlap > 0 => busiest.insert(segment),
+            _ => (),
+        }
+    }
+    (max_overlap, busiest)
+}
+
+pub fn analyse_roster<T: Discrete>(path: &str, mode: Mode, parse: impl Fn(&str) -> Option<T>) -> Result<RosterReport<T>> {
+    let assignments = read_assignments(path, mode, parse)?;
+    let pairs = overlapping_pairs(&assignments);
+    let (max_overlap, busiest_sections) = busiest_sections(&assignments);
+    Ok(RosterReport { assignments, pairs, max_overlap, busiest_sections })
+}
+
+impl<T: Discrete + fmt::Display> RosterReport<T> {
+    /// Print every overlapping pair, as well as the summary.
+    pub fn display_pairs(&self) -> impl fmt::Display + '_ {
+        struct Pairs<'a, T>(&'a RosterReport<T>);
+        impl<T: Discrete + fmt::Display> fmt::Display for Pairs<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let describe = |a: &Assignment<T>| format!("line {} elf {} ({}-{})",
+                    a.line_no, a.elf, a.sections.start(), a.sections.end());
+                for &(i, j, relation) in &self.0.pairs {
+                    let (a, b) = (&self.0.assignments[i], &self.0.assignments[j]);
+                    let relation = match relation {
+                        Relation::Contains => "contains/within",
+                        Relation::Overlaps => "overlaps",
+                    };
+                    writeln!(f, "{} {relation} {}", describe(a), describe(b))?;
+                }
+                write!(f, "{}", self.0)
+            }
+        }
+        Pairs(self)
+    }
+}
+
+impl<T: Discrete + fmt::Display> fmt::Display for RosterReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let contains = self.pairs.iter().filter(|(_, _, r)| *r == Relation::Contains).count();
+        let busiest: Vec<String> = self.busiest_sections.ranges().iter()
+            .map(|r| format!("{}-{}", r.start(), r.end()))
+            .collect();
+        writeln!(f, "Assignments: {}", self.assignments.len())?;
+        writeln!(f, "Overlapping pairs: {} ({contains} where one contains the other)", self.pairs.len())?;
+        writeln!(f, "Maximum simultaneous overlap: {}", self.max_overlap)?;
+        writeln!(f, "Busiest sections: {}", busiest.join(","))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day4::{overlap, parse_number};
+
+    fn assignment<T>(sections: RangeInclusive<T>) -> Assignment<T> {
+        Assignment { line_no: 1, elf: 1, sections }
+    }
+
+    #[test]
+    fn matches_brute_force_pairs() {
+        let assignments = read_assignments("input/day4.txt", Mode::Strict, parse_number::<u32>).unwrap();
+        let mut expected = vec![];
+        for i in 0..assignments.len() {
+            for j in i+1..assignments.len() {
+                let (a, b) = (assignments[i].sections.clone(), assignments[j].sections.clone());
+                if overlap(a.clone(), b.clone()) {
+                    let relation = if subset(a, b) {Relation::Contains} else {Relation::Overlaps};
+                    expected.push((i, j, relation));
+                }
+            }
+        }
+        assert_eq!(overlapping_pairs(&assignments), expected);
+    }
+
+    #[test]
+    fn busiest_sections_reach_the_last_section() {
+        let assignments = [assignment(u32::MAX - 5..=u32::MAX), assignment(u32::MAX..=u32::MAX), assignment(0..=3)];
+        let (max_overlap, busiest) = busiest_sections(&assignments);
+        assert_eq!(max_overlap, 2);
+        assert_eq!(busiest.ranges(), [u32::MAX..=u32::MAX]);
+
+        let (max_overlap, busiest) = busiest_sections(&[assignment(0..=u32::MAX), assignment(7..=9)]);
+        assert_eq!((max_overlap, busiest.ranges()), (2, &[7..=9][..]));
+    }
+
+    #[test]
+    fn handles_64_bit_extremes() {
+        let assignments = [assignment(u64::MAX - 5..=u64::MAX), assignment(u64::MAX..=u64::MAX), assignment(0..=3)];
+        assert_eq!(overlapping_pairs(&assignments), [(0, 1, Relation::Contains)]);
+        let (max_overlap, busiest) = busiest_sections(&assignments);
+        assert_eq!((max_overlap, busiest.ranges()), (2, &[u64::MAX..=u64::MAX][..]));
+
+        let assignments = [assignment(i64::MIN..=i64::MIN + 3), assignment(i64::MIN..=i64::MIN), assignment(i64::MIN + 3..=0)];
+        assert_eq!(overlapping_pairs(&assignments), [(0, 1, Relation::Contains), (0, 2, Relation::Overlaps)]);
+        let (max_overlap, busiest) = busiest_sections(&assignments);
+        assert_eq!((max_overlap, busiest.ranges()), (2, &[i64::MIN..=i64::MIN, i64::MIN + 3..=i64::MIN + 3][..]));
+    }
+}
@@ -1,154 +1,317 @@
 use crate::prelude::*;
+use std::fmt;
+use crane::{Crane, CrateMover9000, CrateMover9001};
+use language::{Statement, NumberedStatement};
 
-type Stack = Vec<char>;
+pub mod crane;
+pub mod animate;
+pub mod execute;
+pub mod history;
+pub mod planner;
+pub mod rope;
+pub mod track;
+pub mod language;
 
-pub fn calculate_part1() -> Result<String>{
-    let file = File::open("input/day5.txt")?;
-    let reader = BufReader::new(file);
-
-    let mut lines = reader.lines();
-
-    // Determine number of stacks (and interpret first line while we have it)
-    let mut stacks: Vec<Stack>;
-    if let Some(line_or_err) = lines.next() {
-        let line = line_or_err?;
-        stacks = parse_first_line(line);
-    } else {
-        return Err( anyhow!("Empty file?") )
-    }
+/// A crate's label, usually a single letter.
+pub type Crate = String;
+pub type Stack = Vec<Crate>;
+/// An instruction and the line it's on, starting at 1.
+pub type NumberedInstruction = (usize, Instruction);
 
-    // Initialise stacks.
-    loop {
-        let line = lines.next().unwrap()?;
-        if line.is_empty() {break} // delimiter between stack and instructions
-        parse_stack(line, &mut stacks);
-    }
-
-    // Parse and execute instructions.
-    for line_or_err in lines {
-        let line = line_or_err?;
-        if line.is_empty() {continue}
+pub fn calculate_part1() -> Result<String>{
+    calculate_with("input/day5.txt", &mut CrateMover9000)
+}
 
-        let instr = parse_instruction(line)?;
-        perform_instruction(&mut stacks, instr);
-    }
+/// Run the rearrangement procedure in any file with any crane, and return the crate on top of each stack.
+pub fn calculate_with(path: &str, crane: &mut dyn Crane) -> Result<String>{
+    let (mut stacks, instructions) = read_input(path)?;
+    execute::execute(&mut stacks, &instructions, crane)?;
 
     // Get the top element from each stack.
     let mut tops = String::new();
     for stack in stacks.iter_mut() {
         if let Some(c) = stack.pop() {
-            tops.push(c);
+            tops.push_str(&c);
         }
     }
     Ok(tops)
 }
 
-/// Parse the first line of the stack to initialise, before doing parsing as normal
-fn parse_first_line(line: String) -> Vec<Stack> {
-    let len = (line.len()+1) / 4;
-    let mut stacks: Vec<Stack> = vec![];
-    for _ in 0..len {
-        stacks.push(vec![])
+/// Read the starting stacks and the list of instructions. Only the puzzle's own `move N from A to B` is allowed.
+pub fn read_input(path: &str) -> Result<(Vec<Stack>, Vec<NumberedInstruction>)> {
+    let (stacks, statements) = read_program(path)?;
+    let instructions = statements.into_iter()
+        .map(|(line_no, statement)| match statement {
+            Statement::Move(instr) => Ok((line_no, instr)),
+            other => Err(anyhow!("Line {line_no}: only 'move N from A to B' can be used here, found '{other}'")),
+        })
+        .collect::<Result<_>>()?;
+    Ok((stacks, instructions))
+}
+
+/// Read the starting stacks and a list of statements in the full instruction language.
+pub fn read_program(path: &str) -> Result<(Vec<Stack>, Vec<NumberedStatement>)> {
+    let input = std::fs::read_to_string(path)?;
+    let mut lines = input.lines().enumerate();
+
+    // Everything up to the first empty line is the drawing of the stacks.
+    let mut drawing = String::new();
+    loop {
+        let (_, line) = lines.next().ok_or(anyhow!("No instructions after the stacks"))?;
+        if line.is_empty() {break} // delimiter between stack and instructions
+        drawing += line;
+        drawing += "\n";
     }
-    parse_stack(line, &mut stacks);
-    stacks
-}
-
-/// Parse the stack string and add containers to stacks.
-fn parse_stack(line: String, stacks: &mut [Stack]){
-    const VALID_FIRST_CHARS: [char;2] = [' ', '['];
-    let second_char = line.chars().nth(1).expect("Empty instruction!");
-    if second_char == '1' {
-        // This is the line after the stack and before instructions, containing the stack numbers 1  2  3  4  ...
-        // So do nothing.
-        return;
+    let stacks = parse_drawing(&drawing)?;
+
+    let statements = language::parse_program(lines.map(|(n, line)| (n + 1, line)))?;
+    Ok((stacks, statements))
+}
+
+/// Problems with the drawing of the stacks. Lines and columns start at 1.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum DrawingError {
+    Empty,
+    /// The last line should number the stacks 1, 2, 3...
+    BadFooter { column: usize, found: String, expected: usize },
+    /// A '[' with no ']' after it.
+    Unclosed { line: usize, column: usize },
+    /// Something other than a crate or a space.
+    Unexpected { line: usize, column: usize, found: char },
+    /// A crate that isn't above exactly one stack number, or is above the same one as another crate on its line.
+    Misaligned { line: usize, column: usize, label: String },
+    /// A crate with empty space under it.
+    Floating { stack: usize, label: String },
+}
+
+impl fmt::Display for DrawingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawingError::Empty => write!(f, "the drawing of the stacks is empty"),
+            DrawingError::BadFooter { column, found, expected } =>
+                write!(f, "footer column {column}: expected stack number {expected}, found '{found}'"),
+            DrawingError::Unclosed { line, column } => write!(f, "line {line} column {column}: crate has no closing ']'"),
+            DrawingError::Unexpected { line, column, found } => write!(f, "line {line} column {column}: unexpected '{found}'"),
+            DrawingError::Misaligned { line, column, label } =>
+                write!(f, "line {line} column {column}: crate [{label}] isn't lined up with a single stack number"),
+            DrawingError::Floating { stack, label } => write!(f, "crate [{label}] in stack {stack} has nothing under it"),
+        }
     }
-    
-    // Iterate over each group of four characters, e.g. "[N] ".
-    for (n, container) in line.as_bytes().chunks(4).enumerate() {
-        let container_contents = container[1] as char;
-        // If the container exists, add it to the stack in the proper place.
-        if container_contents != ' ' {
-            stacks[n].insert(0, container_contents);
+}
+
+impl std::error::Error for DrawingError {}
+
+/// Parse the drawing of the stacks, including the footer with the stack numbers.
+// Each crate belongs to the stack whose number in the footer it's drawn above, so any width of label or stack
+// number works, and trailing spaces don't matter.
+pub fn parse_drawing(drawing: &str) -> Result<Vec<Stack>, DrawingError> {
+    let lines: Vec<&str> = drawing.lines().collect();
+    let (footer, rows) = lines.split_last().ok_or(DrawingError::Empty)?;
+    let columns = parse_footer(footer)?;
+
+    // Work up from the bottom row, so each crate can go straight on top of its stack.
+    let mut stacks: Vec<Stack> = vec![vec![]; columns.len()];
+    for (n, line) in rows.iter().enumerate().rev() {
+        let height = rows.len() - 1 - n;
+        let mut used = vec![false; columns.len()];
+        for (column, label) in parse_row(line, n + 1)? {
+            let last = column + label.chars().count() + 1;
+            let under: Vec<usize> = columns.iter()
+                .enumerate()
+                .filter(|(_, &(start, end))| start <= last && column <= end)
+                .map(|(stack, _)| stack)
+                .collect();
+            let stack = match under[..] {
+                [stack] if !used[stack] => stack,
+                _ => return Err(DrawingError::Misaligned { line: n + 1, column: column + 1, label }),
+            };
+            if stacks[stack].len() != height {
+                return Err(DrawingError::Floating { stack: stack + 1, label });
+            }
+            used[stack] = true;
+            stacks[stack].push(label);
         }
     }
+    Ok(stacks)
+}
 
+/// The first and last column (from 0) of each stack number in the footer.
+fn parse_footer(footer: &str) -> Result<Vec<(usize, usize)>, DrawingError> {
+    let mut columns = vec![];
+    let chars: Vec<char> = footer.chars().collect();
+    let mut start = 0;
+    while start < chars.len() {
+        if chars[start].is_whitespace() {
+            start += 1;
+            continue;
+        }
+        let end = chars[start..].iter().position(|c| c.is_whitespace()).map_or(chars.len(), |len| start + len);
+        let found: String = chars[start..end].iter().collect();
+        let expected = columns.len() + 1;
+        if found != expected.to_string() {
+            return Err(DrawingError::BadFooter { column: start + 1, found, expected });
+        }
+        columns.push((start, end - 1));
+        start = end;
+    }
+    Ok(columns)
 }
 
-struct Instruction {
-    count: usize,
-    source: usize,
-    dest: usize,
+/// The starting column (from 0) and label of each crate in one row of the drawing.
+fn parse_row(line: &str, line_no: usize) -> Result<Vec<(usize, String)>, DrawingError> {
+    let mut crates = vec![];
+    let chars: Vec<char> = line.chars().collect();
+    let mut column = 0;
+    while column < chars.len() {
+        match chars[column] {
+            ' ' => column += 1,
+            '[' => {
+                let close = chars[column+1..].iter()
+                    .position(|&c| c == ']')
+                    .map(|len| column + 1 + len)
+                    .ok_or(DrawingError::Unclosed { line: line_no, column: column + 1 })?;
+                if let Some(bad) = (column+1..close).find(|&i| chars[i].is_whitespace() || chars[i] == '[') {
+                    return Err(DrawingError::Unexpected { line: line_no, column: bad + 1, found: chars[bad] });
+                }
+                if close == column + 1 {
+                    return Err(DrawingError::Unexpected { line: line_no, column: close + 1, found: ']' });
+                }
+                crates.push((column, chars[column+1..close].iter().collect()));
+                column = close + 1;
+            },
+            found => return Err(DrawingError::Unexpected { line: line_no, column: column + 1, found }),
+        }
+    }
+    Ok(crates)
 }
 
-fn parse_instruction(line: String) -> Result<Instruction> {
-    let words: Vec<&str> = line.split_whitespace().collect();
+/// Draw the stacks exactly as the puzzle does, including the footer, so that `parse_drawing` gives them back.
+pub fn render(stacks: &[Stack]) -> String {
+    render_with(stacks, |_, _, text| text.to_string())
+}
 
-    Ok(
-        Instruction {
-            count: words[1].parse()?, 
-            source: words[3].parse::<usize>()? - 1, // one-indexed
-            dest: words[5].parse::<usize>()? - 1,
-        }
-    )
+/// Draw the stacks, passing each crate's (stack, height, "[label]") through `decorate` first.
+// Every column is wide enough for the longest label and stack number, with crates and numbers centred in them.
+// For the puzzle's single-letter crates and up to nine stacks, that's exactly the puzzle's format.
+pub fn render_with(stacks: &[Stack], decorate: impl Fn(usize, usize, &str) -> String) -> String {
+    let longest_label = stacks.iter().flatten().map(|label| label.chars().count()).max().unwrap_or(1);
+    let width = (longest_label + 2).max(stacks.len().to_string().len());
+    let centre = |text: &str, decorated: String| {
+        let space = width - text.chars().count();
+        format!("{}{decorated}{}", " ".repeat(space / 2), " ".repeat(space - space / 2))
+    };
+
+    let mut drawing = String::new();
+    let height = stacks.iter().map(Vec::len).max().unwrap_or(0);
+    for h in (0..height).rev() {
+        let row: Vec<String> = stacks.iter()
+            .enumerate()
+            .map(|(n, stack)| match stack.get(h) {
+                Some(label) => {
+                    let text = format!("[{label}]");
+                    centre(&text, decorate(n, h, &text))
+                },
+                None => " ".repeat(width),
+            })
+            .collect();
+        drawing += &row.join(" ");
+        drawing += "\n";
+    }
+    let footer: Vec<String> = (1..=stacks.len()).map(|n| centre(&n.to_string(), n.to_string())).collect();
+    drawing += &footer.join(" ");
+    drawing += "\n";
+    drawing
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Instruction {
+    pub count: usize,
+    /// Zero-indexed.
+    pub source: usize,
+    /// Zero-indexed.
+    pub dest: usize,
 }
 
-fn perform_instruction(stacks: &mut [Stack], instr: Instruction) {
-    for _ in 0..instr.count {
-        let container = stacks[instr.source].pop().expect("Tried to pop empty stack!");
-        stacks[instr.dest].push(container);
+impl fmt::Display for Instruction {
+    // The puzzle's own syntax, one-indexed.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.source + 1, self.dest + 1)
     }
 }
 
 /* Part 2 begins here */
 
-// Identical to above, except use perform_instruction_9001 instead.
+// Identical to above, except with the CrateMover 9001.
 pub fn calculate_part2() -> Result<String>{
-    let file = File::open("input/day5.txt")?;
-    let reader = BufReader::new(file);
-
-    let mut lines = reader.lines();
-
-    // Determine number of stacks (also interpret first line)
-    let mut stacks: Vec<Stack>;
-    if let Some(line_or_err) = lines.next() {
-        let line = line_or_err?;
-        stacks = parse_first_line(line);
-    } else {
-        return Err( anyhow!("Empty file?") )
-    }
+    calculate_with("input/day5.txt", &mut CrateMover9001)
+}
 
-    // Initialise stacks.
-    loop {
-        let line = lines.next().unwrap()?;
-        if line.is_empty() {break} // delimiter between stack and instructions
-        parse_stack(line, &mut stacks);
-    }
+/// One stack per string, bottom crate first, for tests.
+#[cfg(test)]
+pub fn stacks(stacks: &[&str]) -> Vec<Stack> {
+    stacks.iter().map(|stack| stack.chars().map(String::from).collect()).collect()
+}
 
-    // Parse and execute instructions.
-    for line_or_err in lines {
-        let line = line_or_err?;
-        if line.is_empty() {continue}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let instr = parse_instruction(line)?;
-        perform_instruction_9001(&mut stacks, instr);
+    const EXAMPLE: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+
+    #[test]
+    fn render_matches_puzzle_drawing() {
+        let stacks = parse_drawing(EXAMPLE).unwrap();
+        assert_eq!(stacks, vec![vec!["Z", "N"], vec!["M", "C", "D"], vec!["P"]]);
+        assert_eq!(render(&stacks), EXAMPLE);
+
+        let input = std::fs::read_to_string("input/day5.txt").unwrap();
+        let drawing = &input[..input.find("\n\n").unwrap() + 1];
+        assert_eq!(render(&parse_drawing(drawing).unwrap()), drawing);
     }
 
-    // Get the top element from each stack.
-    let mut tops = String::new();
-    for stack in stacks.iter_mut() {
-        if let Some(c) = stack.pop() {
-            tops.push(c);
+    #[test]
+    fn round_trips_every_state() {
+        let (mut stacks, instructions) = read_input("input/day5.txt").unwrap();
+        let mut crane = CrateMover9001;
+        for (_, instr) in &instructions {
+            crane.perform(&mut stacks, instr).unwrap();
+            let drawing = render(&stacks);
+            assert_eq!(parse_drawing(&drawing).unwrap(), stacks);
+            assert_eq!(render(&parse_drawing(&drawing).unwrap()), drawing);
         }
+
+        // Including when every stack is empty.
+        let empty = vec![vec![]; 4];
+        assert_eq!(parse_drawing(&render(&empty)).unwrap(), empty);
     }
-    Ok(tops)
-}
 
-/// Move crates from source to dest while maintaining their internal order (i.e. not like a stack)
-fn perform_instruction_9001(stacks: &mut [Stack], instr: Instruction) {
-    let start_pos = (stacks[instr.source].len() - 1) - (instr.count - 1).max(0);
-    let containers: Vec<char> = stacks[instr.source].drain(start_pos..).collect();
-    for container in containers {
-        stacks[instr.dest].push(container);
+    #[test]
+    fn parses_wide_drawings() {
+        // Twelve stacks, labels of different lengths, and no trailing spaces.
+        let stacks: Vec<Stack> = (1..=12)
+            .map(|n| (0..n % 4).map(|h| "X".repeat(h + 1) + &n.to_string()).collect())
+            .collect();
+        let drawing = render(&stacks);
+        assert_eq!(parse_drawing(&drawing).unwrap(), stacks);
+        let trimmed: String = drawing.lines().map(|line| line.trim_end().to_string() + "\n").collect();
+        assert_eq!(parse_drawing(&trimmed).unwrap(), stacks);
+
+        let drawing = "[AB]      [C]\n[D]  [EFG]  [H]\n  1    2    3\n";
+        assert_eq!(parse_drawing(drawing).unwrap(), vec![vec!["D", "AB"], vec!["EFG"], vec!["H", "C"]]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_bad_drawings() {
+        assert_eq!(parse_drawing(""), Err(DrawingError::Empty));
+        assert_eq!(parse_drawing("[A]\n 1   3 \n"),
+            Err(DrawingError::BadFooter { column: 6, found: "3".to_string(), expected: 2 }));
+        assert_eq!(parse_drawing("  [A]\n 1   2 \n"),
+            Err(DrawingError::Misaligned { line: 1, column: 3, label: "A".to_string() }));
+        assert_eq!(parse_drawing("[ABCDE]\n 1   2 \n"),
+            Err(DrawingError::Misaligned { line: 1, column: 1, label: "ABCDE".to_string() }));
+        assert_eq!(parse_drawing("[A]\n    [B]\n 1   2 \n"),
+            Err(DrawingError::Floating { stack: 1, label: "A".to_string() }));
+        assert_eq!(parse_drawing("[A\n 1 \n"), Err(DrawingError::Unclosed { line: 1, column: 1 }));
+        assert_eq!(parse_drawing("[A] x\n 1 \n"), Err(DrawingError::Unexpected { line: 1, column: 5, found: 'x' }));
+    }
+}
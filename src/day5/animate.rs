@@ -0,0 +1,120 @@
+use crate::prelude::*;
+use std::sync::mpsc;
+use std::time::Duration;
+use super::{Stack, read_program, render_with};
+use super::crane::Crane;
+use super::execute::{crane_move, run};
+use super::language::{Statement, NumberedStatement};
+
+/**/
+// Draw the stacks after every instruction, in the same [X] format as the puzzle input, with the crates that just
+// moved highlighted.
+// Playing in the terminal reads commands a line at a time on a separate thread, so the animation keeps going until
+// Enter is pressed, and doesn't need the terminal put into raw mode.
+/**/
+
+const HIGHLIGHT: &str = "\x1b[1;33m";
+const RESET: &str = "\x1b[0m";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// One state of the stacks, and how it was reached.
+pub struct Frame {
+    /// 0 for the starting state, otherwise the statement just carried out (starting at 1).
+    pub step: usize,
+    pub statement: Option<Statement>,
+    pub stacks: Vec<Stack>,
+    /// (stack, height) of each crate that just moved, with 0 the bottom crate.
+    pub moved: Vec<(usize, usize)>,
+}
+
+/// Every frame from the starting state to the end of the procedure.
+pub fn frames(stacks: Vec<Stack>, statements: &[NumberedStatement], crane: &mut dyn Crane) -> Result<Vec<Frame>> {
+    let mut frames = vec![Frame { step: 0, statement: None, stacks: stacks.clone(), moved: vec![] }];
+    let mut stacks = stacks;
+    for (n, numbered) in statements.iter().enumerate() {
+        let instr = crane_move(&stacks, &numbered.1);
+        run(&mut stacks, std::slice::from_ref(numbered), crane)?;
+        let moved = match instr {
+            // Crates moved onto the stack they came from stay where they were.
+            Some(instr) if instr.source == instr.dest => vec![],
+            // Whatever the crane, the moved crates end up on top of the destination.
+            Some(instr) => {
+                let height = stacks[instr.dest].len();
+                (height - instr.count..height).map(|h| (instr.dest, h)).collect()
+            },
+            // Swapping, reversing and rotating move whole stacks.
+            None => numbered.1.stacks().into_iter().flat_map(|n| (0..stacks[n].len()).map(move |h| (n, h))).collect(),
+        };
+        frames.push(Frame { step: n + 1, statement: Some(numbered.1), stacks: stacks.clone(), moved });
+    }
+    Ok(frames)
+}
+
+impl Frame {
+    /// Draw the frame, highlighting the moved crates with ANSI colours if `colour` is set.
+    pub fn render(&self, colour: bool) -> String {
+        let mut output = match self.statement {
+            Some(statement) => format!("Step {}: {statement}\n", self.step),
+            None => "Start\n".to_string(),
+        };
+        output += &render_with(&self.stacks, |n, h, text| match colour && self.moved.contains(&(n, h)) {
+            true => format!("{HIGHLIGHT}{text}{RESET}"),
+            false => text.to_string(),
+        });
+        output
+    }
+}
+
+/// Write every frame to a file, one after another, without colours.
+pub fn dump_frames(path: &str, crane: &mut dyn Crane, output: &str) -> Result<()> {
+    let (stacks, statements) = read_program(path)?;
+    let mut file = File::create(output)?;
+    for frame in frames(stacks, &statements, crane)? {
+        writeln!(file, "{}", frame.render(false))?;
+    }
+    Ok(())
+}
+
+/// Play the animation in the terminal, showing a new frame every `delay`.
+/// While playing, Enter pauses. While paused, Enter steps forward one frame, "c" continues and "q" quits.
+pub fn play(path: &str, crane: &mut dyn Crane, delay: Duration, start_paused: bool) -> Result<()> {
+    let (stacks, statements) = read_program(path)?;
+    let frames = frames(stacks, &statements, crane)?;
+
+    let (sender, commands) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {break};
+            if sender.send(line.trim().to_string()).is_err() {break}
+        }
+    });
+
+    let mut paused = start_paused;
+    let mut frame = 0;
+    loop {
+        print!("{CLEAR_SCREEN}{}", frames[frame].render(true));
+        if paused {
+            println!("[paused] Enter: step, c: continue, q: quit");
+        }
+        std::io::stdout().flush()?;
+        if frame + 1 == frames.len() {break}
+
+        let command = if paused {
+            commands.recv().ok()
+        } else {
+            commands.recv_timeout(delay).ok()
+        };
+        match (command.as_deref(), paused) {
+            (Some("q"), _) => break,
+            (Some("c"), _) => {
+                paused = false;
+                frame += 1;
+            },
+            // Enter while playing pauses without moving on.
+            (Some(_), false) => paused = true,
+            // Enter while paused, or the delay running out while playing.
+            _ => frame += 1,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,347 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{Stack, Instruction};
+
+/**/
+// Different models of crane, which all follow the same instructions but move the crates differently.
+// Every model works by lifting some crates off the top of one stack and putting them down on another, either in
+// the same order or reversed (which is what moving them one at a time does).
+/**/
+
+pub trait Crane {
+    fn name(&self) -> String;
+    /// Work out how to carry out one instruction, given the height of every stack. If it can't be done, says why.
+    /// Every lift is from the instruction's source to its destination.
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError>;
+    /// Carry out one instruction. If it can't be done, the stacks are left as they were.
+    fn perform(&mut self, stacks: &mut [Stack], instr: &Instruction) -> Result<(), CraneError> {
+        perform_on(self, stacks, instr)
+    }
+    /// Forget the last lift planned, for cranes that keep track of what they've done.
+    fn undo(&mut self) {}
+}
+
+/// Some crates lifted off the top of one stack and put down on another in one go.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Lift {
+    pub count: usize,
+    /// The crates are put down upside down.
+    pub reversed: bool,
+}
+
+/// Carry out one instruction on stacks of anything, like crate ids instead of labels.
+/// Moving crates from a stack onto itself leaves them as they were, so the crane isn't asked to lift anything.
+pub fn perform_on<T, C: Crane + ?Sized>(crane: &mut C, stacks: &mut [Vec<T>], instr: &Instruction) -> Result<(), CraneError> {
+    let heights: Vec<usize> = stacks.iter().map(Vec::len).collect();
+    if instr.source == instr.dest {
+        return check(&heights, instr);
+    }
+    for lift in crane.lifts(&heights, instr)? {
+        move_crates(stacks, instr.source, instr.dest, lift);
+    }
+    Ok(())
+}
+
+/// Let the crane forget `instr` once it's been undone. Only instructions it lifted crates for reach the crane.
+pub fn undo_on<C: Crane + ?Sized>(crane: &mut C, instr: &Instruction) {
+    if instr.source != instr.dest {
+        crane.undo();
+    }
+}
+
+/// Reasons a crane can't carry out an instruction. Stacks are numbered from 1.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum CraneError {
+    NoSuchStack { stack: usize, stack_count: usize },
+    NotEnoughCrates { stack: usize, requested: usize, available: usize },
+    TooHigh { stack: usize, height: usize, limit: usize },
+    /// The crane can't lift any crates at all.
+    ZeroLift,
+    /// A range of stacks where the first comes after the last.
+    BackwardsRange { first: usize, last: usize },
+}
+
+impl fmt::Display for CraneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CraneError::NoSuchStack { stack, stack_count } => write!(f, "there is no stack {stack} (only {stack_count})"),
+            CraneError::NotEnoughCrates { stack, requested, available } =>
+                write!(f, "tried to move {requested} crates from stack {stack}, which only has {available}"),
+            CraneError::TooHigh { stack, height, limit } =>
+                write!(f, "stack {stack} would be {height} crates high, over the limit of {limit}"),
+            CraneError::ZeroLift => write!(f, "a crane that lifts 0 crates can't move anything"),
+            CraneError::BackwardsRange { first, last } => write!(f, "stack {first} comes after stack {last}"),
+        }
+    }
+}
+
+impl std::error::Error for CraneError {}
+
+/// Make sure both stacks exist and there are enough crates to move.
+fn check(heights: &[usize], instr: &Instruction) -> Result<(), CraneError> {
+    let stack_count = heights.len();
+    for stack in [instr.source, instr.dest] {
+        if stack >= stack_count {
+            return Err(CraneError::NoSuchStack { stack: stack + 1, stack_count });
+        }
+    }
+    let available = heights[instr.source];
+    if instr.count > available {
+        return Err(CraneError::NotEnoughCrates { stack: instr.source + 1, requested: instr.count, available });
+    }
+    Ok(())
+}
+
+/// Move the top crates of `source` onto a different stack `dest`, which have already been checked.
+fn move_crates<T>(stacks: &mut [Vec<T>], source: usize, dest: usize, lift: Lift) {
+    let height = stacks[source].len();
+
+    // Move the crates straight from one stack to the other, without collecting them anywhere in between.
+    let (source, dest) = if source < dest {
+        let (low, high) = stacks.split_at_mut(dest);
+        (&mut low[source], &mut high[0])
+    } else {
+        let (low, high) = stacks.split_at_mut(source);
+        (&mut high[0], &mut low[dest])
+    };
+    let crates = source.drain(height - lift.count..);
+    if lift.reversed {
+        dest.extend(crates.rev());
+    } else {
+        dest.extend(crates);
+    }
+}
+
+/// Moves one crate at a time (part 1).
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        check(heights, instr)?;
+        Ok(vec![Lift { count: instr.count, reversed: true }])
+    }
+}
+
+/// Moves all the crates at once, maintaining their internal order (part 2).
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        check(heights, instr)?;
+        Ok(vec![Lift { count: instr.count, reversed: false }])
+    }
+}
+
+/// Moves crates in lifts of at most `max` at a time, keeping the order within each lift.
+/// With `max` = 1 this is the same as the 9000.
+pub struct MaxPerLift {
+    pub max: usize,
+}
+
+impl Crane for MaxPerLift {
+    fn name(&self) -> String {
+        format!("at most {} per lift", self.max)
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        if self.max == 0 {
+            return Err(CraneError::ZeroLift);
+        }
+        check(heights, instr)?;
+        let mut lifts = vec![];
+        let mut remaining = instr.count;
+        while remaining > 0 {
+            let count = remaining.min(self.max);
+            lifts.push(Lift { count, reversed: false });
+            remaining -= count;
+        }
+        Ok(lifts)
+    }
+}
+
+/// Moves all the crates at once like the 9001, but every second lift gets flipped over.
+#[derive(Default)]
+pub struct Alternating {
+    /// Lifts made so far.
+    pub lifts: usize,
+}
+
+impl Crane for Alternating {
+    fn name(&self) -> String {
+        "reverses every other lift".to_string()
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        check(heights, instr)?;
+        let reversed = !self.lifts.is_multiple_of(2);
+        self.lifts += 1;
+        Ok(vec![Lift { count: instr.count, reversed }])
+    }
+
+    fn undo(&mut self) {
+        self.lifts = self.lifts.saturating_sub(1);
+    }
+}
+
+/// Any other crane, but refuses to stack crates higher than `max_height`.
+pub struct HeightLimited<C: Crane> {
+    pub crane: C,
+    pub max_height: usize,
+}
+
+impl<C: Crane> Crane for HeightLimited<C> {
+    fn name(&self) -> String {
+        format!("{}, limited to height {}", self.crane.name(), self.max_height)
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        check(heights, instr)?;
+        // Moving crates from a stack onto itself leaves it the same height.
+        let height = heights[instr.dest] + if instr.source == instr.dest {0} else {instr.count};
+        if height > self.max_height {
+            return Err(CraneError::TooHigh { stack: instr.dest + 1, height, limit: self.max_height });
+        }
+        self.crane.lifts(heights, instr)
+    }
+
+    fn undo(&mut self) {
+        self.crane.undo();
+    }
+}
+
+/// Pick a crane by name: "9000", "9001", "max-lift:K", "alternating", or "height-limit:H" optionally followed by
+/// ":" and another name for the crane to limit (9001 if not given).
+pub fn crane_by_name(name: &str) -> Result<Box<dyn Crane>> {
+    let number = |value: &str| value.parse::<usize>().map_err(|_| anyhow!("'{value}' is not a number in crane '{name}'"));
+    let crane: Box<dyn Crane> = match name.split_once(':') {
+        None if name == "9000" => Box::new(CrateMover9000),
+        None if name == "9001" => Box::new(CrateMover9001),
+        None if name == "alternating" => Box::new(Alternating::default()),
+        Some(("max-lift", max)) => Box::new(MaxPerLift { max: number(max)? }),
+        Some(("height-limit", rest)) => {
+            let (max_height, inner) = rest.split_once(':').unwrap_or((rest, "9001"));
+            Box::new(HeightLimited { crane: crane_by_name(inner)?, max_height: number(max_height)? })
+        },
+        _ => return Err(anyhow!("Unknown crane '{name}'")),
+    };
+    Ok(crane)
+}
+
+impl<C: Crane + ?Sized> Crane for &mut C {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        (**self).lifts(heights, instr)
+    }
+
+    fn undo(&mut self) {
+        (**self).undo();
+    }
+}
+
+impl Crane for Box<dyn Crane> {
+    fn name(&self) -> String {
+        self.as_ref().name()
+    }
+
+    fn lifts(&mut self, heights: &[usize], instr: &Instruction) -> Result<Vec<Lift>, CraneError> {
+        self.as_mut().lifts(heights, instr)
+    }
+
+    fn undo(&mut self) {
+        self.as_mut().undo();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::stacks;
+
+    fn mv(count: usize, source: usize, dest: usize) -> Instruction {
+        Instruction { count, source, dest }
+    }
+
+    #[test]
+    fn max_per_lift_keeps_order_within_each_lift() {
+        let mut state = stacks(&["ABCDE", ""]);
+        MaxPerLift { max: 2 }.perform(&mut state, &mv(5, 0, 1)).unwrap();
+        assert_eq!(state, stacks(&["", "DEBCA"]));
+        assert_eq!(MaxPerLift { max: 0 }.perform(&mut state, &mv(1, 1, 0)), Err(CraneError::ZeroLift));
+    }
+
+    #[test]
+    fn alternating_flips_every_other_lift() {
+        let mut crane = Alternating::default();
+        let mut state = stacks(&["ABC", "", ""]);
+        crane.perform(&mut state, &mv(2, 0, 1)).unwrap();
+        crane.perform(&mut state, &mv(2, 1, 2)).unwrap();
+        assert_eq!(state, stacks(&["A", "", "CB"]));
+        assert_eq!(crane.lifts, 2);
+
+        // A failed lift doesn't count, and undoing one means the next lift is the same way round again.
+        assert!(crane.perform(&mut state, &mv(3, 2, 0)).is_err());
+        crane.undo();
+        crane.perform(&mut state, &mv(2, 2, 0)).unwrap();
+        assert_eq!(state, stacks(&["ABC", "", ""]));
+    }
+
+    #[test]
+    fn same_stack_moves_change_nothing() {
+        let cranes: [&mut dyn Crane; 4] =
+            [&mut CrateMover9000, &mut CrateMover9001, &mut MaxPerLift { max: 2 }, &mut Alternating { lifts: 1 }];
+        for crane in cranes {
+            let mut state = stacks(&["ABC", "D"]);
+            crane.perform(&mut state, &mv(3, 0, 0)).unwrap();
+            assert_eq!(state, stacks(&["ABC", "D"]), "{}", crane.name());
+            assert_eq!(crane.perform(&mut state, &mv(2, 1, 1)),
+                Err(CraneError::NotEnoughCrates { stack: 2, requested: 2, available: 1 }));
+        }
+
+        // Nothing is lifted, so the next lift is still the same way round.
+        let mut crane = Alternating::default();
+        let mut state = stacks(&["ABC", ""]);
+        crane.perform(&mut state, &mv(2, 0, 0)).unwrap();
+        undo_on(&mut crane, &mv(2, 0, 0));
+        crane.perform(&mut state, &mv(2, 0, 0)).unwrap();
+        assert_eq!(crane.lifts, 0);
+    }
+
+    #[test]
+    fn height_limited_refuses_tall_stacks() {
+        let mut crane = HeightLimited { crane: CrateMover9000, max_height: 3 };
+        let mut state = stacks(&["ABC", "DE"]);
+        assert_eq!(crane.perform(&mut state, &mv(2, 0, 1)), Err(CraneError::TooHigh { stack: 2, height: 4, limit: 3 }));
+        assert_eq!(state, stacks(&["ABC", "DE"]));
+
+        // Moving onto the same stack doesn't make it any taller.
+        crane.perform(&mut state, &mv(3, 0, 0)).unwrap();
+        crane.perform(&mut state, &mv(1, 0, 1)).unwrap();
+        assert_eq!(state, stacks(&["AB", "DEC"]));
+        assert_eq!(crane.perform(&mut state, &mv(1, 2, 0)), Err(CraneError::NoSuchStack { stack: 3, stack_count: 2 }));
+    }
+
+    #[test]
+    fn names_cranes() {
+        assert_eq!(crane_by_name("height-limit:5").unwrap().name(), "CrateMover 9001, limited to height 5");
+        assert_eq!(crane_by_name("height-limit:5:max-lift:2").unwrap().name(), "at most 2 per lift, limited to height 5");
+        for (name, error) in [
+            ("9002", "Unknown crane '9002'"),
+            ("max-lift:two", "'two' is not a number in crane 'max-lift:two'"),
+            ("height-limit:5:9002", "Unknown crane '9002'"),
+            ("height-limit:-1", "'-1' is not a number in crane 'height-limit:-1'"),
+        ] {
+            assert_eq!(crane_by_name(name).err().unwrap().to_string(), error);
+        }
+    }
+}
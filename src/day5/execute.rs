@@ -0,0 +1,276 @@
+use std::fmt;
+use super::{Stack, Instruction, NumberedInstruction, render};
+use super::crane::{Crane, CraneError, perform_on, undo_on};
+use super::language::{Statement, NumberedStatement};
+
+/**/
+// Run a list of instructions, stopping at the first one that can't be done instead of panicking.
+/**/
+
+/// An instruction that couldn't be carried out, and the stacks just before it.
+#[derive(Clone, Debug)]
+pub struct ExecutionError {
+    pub line_no: usize,
+    pub statement: Statement,
+    pub error: CraneError,
+    pub stacks: Vec<Stack>,
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Line {} ({}): {}", self.line_no, self.statement, self.error)?;
+        writeln!(f, "Stacks before this instruction:")?;
+        write!(f, "{}", render(&self.stacks))
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+/// Carry out each instruction in turn. If one fails, the stacks are left as they were just before it.
+pub fn execute(stacks: &mut [Stack], instructions: &[NumberedInstruction], crane: &mut dyn Crane) -> Result<(), ExecutionError> {
+    for &(line_no, instruction) in instructions {
+        crane.perform(stacks, &instruction)
+            .map_err(|error| ExecutionError { line_no, statement: Statement::Move(instruction), error, stacks: stacks.to_vec() })?;
+    }
+    Ok(())
+}
+
+/// Make sure every stack exists.
+fn check_stacks<T>(stacks: &[Vec<T>], wanted: &[usize]) -> Result<(), CraneError> {
+    match wanted.iter().find(|&&stack| stack >= stacks.len()) {
+        Some(stack) => Err(CraneError::NoSuchStack { stack: stack + 1, stack_count: stacks.len() }),
+        None => Ok(()),
+    }
+}
+
+/// The instruction the crane is given for a statement, if it uses the crane at all.
+pub fn crane_move<T>(stacks: &[Vec<T>], statement: &Statement) -> Option<Instruction> {
+    match *statement {
+        Statement::Move(instruction) => Some(instruction),
+        Statement::MoveAll { source, dest } => Some(Instruction { count: stacks.get(source).map_or(0, Vec::len), source, dest }),
+        Statement::Swap(..) | Statement::Reverse(_) | Statement::Rotate { .. } => None,
+    }
+}
+
+/// Carry out a statement in the instruction language. Anything that moves crates is done by the crane.
+/// Works on any kind of crate, like `perform_on`.
+pub fn perform<T>(stacks: &mut [Vec<T>], statement: &Statement, crane: &mut dyn Crane) -> Result<(), CraneError> {
+    match *statement {
+        Statement::Move(_) | Statement::MoveAll { .. } => {
+            let instruction = crane_move(stacks, statement).expect("Moves always use the crane");
+            perform_on(crane, stacks, &instruction)
+        },
+        Statement::Swap(a, b) => {
+            check_stacks(stacks, &[a, b])?;
+            stacks.swap(a, b);
+            Ok(())
+        },
+        Statement::Reverse(stack) => {
+            check_stacks(stacks, &[stack])?;
+            stacks[stack].reverse();
+            Ok(())
+        },
+        Statement::Rotate { first, last, by } => {
+            check_stacks(stacks, &[first, last])?;
+            if first > last {
+                return Err(CraneError::BackwardsRange { first: first + 1, last: last + 1 });
+            }
+            let range = &mut stacks[first..=last];
+            range.rotate_right(by.rem_euclid(range.len() as isize) as usize);
+            Ok(())
+        },
+    }
+}
+
+/// Same as `execute`, but for statements in the instruction language.
+pub fn run(stacks: &mut [Stack], statements: &[NumberedStatement], crane: &mut dyn Crane) -> Result<(), ExecutionError> {
+    for &(line_no, statement) in statements {
+        perform(stacks, &statement, crane)
+            .map_err(|error| ExecutionError { line_no, statement, error, stacks: stacks.to_vec() })?;
+    }
+    Ok(())
+}
+
+/// Check that every instruction can be carried out without changing `stacks`, and return how they'd end up.
+/// Every lift is undone afterwards, so cranes that remember earlier lifts are ready for the real run.
+pub fn dry_run(stacks: &[Stack], instructions: &[NumberedInstruction], crane: &mut dyn Crane) -> Result<Vec<Stack>, ExecutionError> {
+    let statements: Vec<NumberedStatement> = instructions.iter()
+        .map(|&(line_no, instruction)| (line_no, Statement::Move(instruction)))
+        .collect();
+    dry_run_program(stacks, &statements, crane)
+}
+
+/// Same as `dry_run`, but for statements in the instruction language.
+pub fn dry_run_program(stacks: &[Stack], statements: &[NumberedStatement], crane: &mut dyn Crane)
+    -> Result<Vec<Stack>, ExecutionError> {
+    let mut stacks = stacks.to_vec();
+    let mut performed = 0;
+    let result = statements.iter().try_for_each(|numbered| {
+        run(&mut stacks, std::slice::from_ref(numbered), crane)?;
+        performed += 1;
+        Ok(())
+    });
+    for (_, statement) in statements[..performed].iter().rev() {
+        // Only moves use the crane, and undoing one doesn't depend on how many crates it moved.
+        if let Statement::Move(Instruction { source, dest, .. }) | Statement::MoveAll { source, dest } = *statement {
+            undo_on(crane, &Instruction { count: 0, source, dest });
+        }
+    }
+    result.map(|()| stacks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::{read_input, stacks};
+    use crate::day5::crane::{CrateMover9000, CrateMover9001, MaxPerLift, Alternating, HeightLimited};
+    use crate::day5::language::{parse_program, SyntaxErrorKind};
+
+    fn error(stacks: &mut [Stack], count: usize, source: usize, dest: usize, crane: &mut dyn Crane) -> CraneError {
+        execute(stacks, &[(1, Instruction { count, source, dest })], crane).unwrap_err().error
+    }
+
+    #[test]
+    fn reports_crane_errors() {
+        let mut state = stacks(&["AB", "C"]);
+        assert_eq!(error(&mut state, 3, 0, 1, &mut CrateMover9000),
+            CraneError::NotEnoughCrates { stack: 1, requested: 3, available: 2 });
+        assert_eq!(error(&mut state, 1, 0, 2, &mut CrateMover9001), CraneError::NoSuchStack { stack: 3, stack_count: 2 });
+        assert_eq!(error(&mut state, 2, 0, 1, &mut HeightLimited { crane: CrateMover9001, max_height: 2 }),
+            CraneError::TooHigh { stack: 2, height: 3, limit: 2 });
+        assert_eq!(error(&mut state, 1, 0, 1, &mut MaxPerLift { max: 0 }), CraneError::ZeroLift);
+        assert_eq!(state, stacks(&["AB", "C"]));
+    }
+
+    #[test]
+    fn parser_rejects_stack_zero() {
+        let error = parse_program([(1, "move 1 from 0 to 1")]).unwrap_err();
+        assert_eq!((error.line, error.kind), (1, SyntaxErrorKind::StackZero));
+    }
+
+    #[test]
+    fn error_has_the_stacks_before_the_failing_line() {
+        let instructions = [
+            (1, Instruction { count: 1, source: 0, dest: 1 }),
+            (3, Instruction { count: 2, source: 1, dest: 2 }),
+            (4, Instruction { count: 5, source: 0, dest: 2 }),
+        ];
+        let mut state = stacks(&["ABC", "D", ""]);
+        let error = execute(&mut state, &instructions, &mut CrateMover9000).unwrap_err();
+        assert_eq!(error.line_no, 4);
+        assert_eq!(error.statement, Statement::Move(instructions[2].1));
+        assert_eq!(error.stacks, stacks(&["AB", "", "CD"]));
+        assert_eq!(state, error.stacks);
+    }
+
+    #[test]
+    fn dry_run_changes_nothing() {
+        let (start, instructions) = read_input("input/day5.txt").unwrap();
+        let original = start.clone();
+        let mut crane = Alternating::default();
+        let end = dry_run(&start, &instructions, &mut crane).unwrap();
+        assert_eq!(start, original);
+        assert_eq!(crane.lifts, 0);
+
+        // The real run ends up the same, because the crane starts from the same state.
+        let mut state = start.clone();
+        execute(&mut state, &instructions, &mut crane).unwrap();
+        assert_eq!(state, end);
+
+        // A failed dry run undoes the lifts before the failure too.
+        let mut crane = Alternating::default();
+        let mut broken = instructions.clone();
+        broken[10].1.count = 1000;
+        assert_eq!(dry_run(&start, &broken, &mut crane).unwrap_err().line_no, broken[10].0);
+        assert_eq!(crane.lifts, 0);
+    }
+
+    #[test]
+    fn dry_runs_the_whole_language() {
+        let program = ["move all from 1 to 2", "swap 1 3", "move 1 from 2 to 2", "rotate 1..3 by 1", "move 1 from 3 to 1"];
+        let statements = parse_program(program.into_iter().enumerate().map(|(n, line)| (n + 1, line))).unwrap();
+        let start = stacks(&["AB", "C", "D"]);
+        let mut crane = Alternating::default();
+        let end = dry_run_program(&start, &statements, &mut crane).unwrap();
+        assert_eq!(crane.lifts, 0);
+        let mut state = start.clone();
+        run(&mut state, &statements, &mut crane).unwrap();
+        assert_eq!(end, stacks(&["B", "D", "CA"]));
+        assert_eq!(state, end);
+
+        // Undoing a failed dry run skips the statements that don't use the crane.
+        let mut crane = Alternating::default();
+        let broken = parse_program([(1, "move all from 1 to 2"), (2, "reverse 1"), (3, "swap 1 4")]).unwrap();
+        assert_eq!(dry_run_program(&start, &broken, &mut crane).unwrap_err().line_no, 3);
+        assert_eq!(crane.lifts, 0);
+    }
+
+    fn perform_all(state: &mut [Stack], statements: &[Statement], crane: &mut dyn Crane) -> Result<(), CraneError> {
+        statements.iter().try_for_each(|statement| perform(state, statement, crane))
+    }
+
+    #[test]
+    fn swaps_and_reverses_stacks() {
+        let mut state = stacks(&["AB", "C", "DE"]);
+        let statements = [Statement::Swap(0, 2), Statement::Reverse(0), Statement::Swap(1, 1)];
+        perform_all(&mut state, &statements, &mut CrateMover9000).unwrap();
+        assert_eq!(state, stacks(&["ED", "C", "AB"]));
+        assert_eq!(perform(&mut state, &Statement::Swap(1, 3), &mut CrateMover9000),
+            Err(CraneError::NoSuchStack { stack: 4, stack_count: 3 }));
+        assert_eq!(perform(&mut state, &Statement::Reverse(5), &mut CrateMover9000),
+            Err(CraneError::NoSuchStack { stack: 6, stack_count: 3 }));
+        assert_eq!(state, stacks(&["ED", "C", "AB"]));
+    }
+
+    #[test]
+    fn rotates_stacks() {
+        let rotate = |by| {
+            let mut state = stacks(&["A", "B", "C", "D"]);
+            perform(&mut state, &Statement::Rotate { first: 0, last: 2, by }, &mut CrateMover9000).unwrap();
+            state
+        };
+        // Stacks move towards `last`, and the ones pushed off the end come round to `first`.
+        assert_eq!(rotate(1), stacks(&["C", "A", "B", "D"]));
+        assert_eq!(rotate(-1), stacks(&["B", "C", "A", "D"]));
+        assert_eq!(rotate(0), stacks(&["A", "B", "C", "D"]));
+        assert_eq!(rotate(3), rotate(0));
+        assert_eq!(rotate(4), rotate(1));
+        assert_eq!(rotate(-4), rotate(-1));
+
+        let mut state = stacks(&["A", "B"]);
+        assert_eq!(perform(&mut state, &Statement::Rotate { first: 0, last: 2, by: 1 }, &mut CrateMover9000),
+            Err(CraneError::NoSuchStack { stack: 3, stack_count: 2 }));
+        assert_eq!(state, stacks(&["A", "B"]));
+        // The parser never makes a backwards range, but anything else might.
+        assert_eq!(perform(&mut state, &Statement::Rotate { first: 1, last: 0, by: 1 }, &mut CrateMover9000),
+            Err(CraneError::BackwardsRange { first: 2, last: 1 }));
+        assert_eq!(state, stacks(&["A", "B"]));
+    }
+
+    #[test]
+    fn moves_all_crates_with_the_crane() {
+        let move_all = |state: &[&str], crane: &mut dyn Crane| {
+            let mut state = stacks(state);
+            perform(&mut state, &Statement::MoveAll { source: 0, dest: 1 }, crane).map(|()| state)
+        };
+        assert_eq!(move_all(&["AB", "C"], &mut CrateMover9000), Ok(stacks(&["", "CBA"])));
+        assert_eq!(move_all(&["AB", "C"], &mut CrateMover9001), Ok(stacks(&["", "CAB"])));
+        assert_eq!(move_all(&["", "C"], &mut CrateMover9000), Ok(stacks(&["", "C"])));
+        let mut state = stacks(&["AB"]);
+        assert_eq!(perform(&mut state, &Statement::MoveAll { source: 0, dest: 1 }, &mut CrateMover9000),
+            Err(CraneError::NoSuchStack { stack: 2, stack_count: 1 }));
+    }
+
+    #[test]
+    fn move_all_respects_the_height_limit() {
+        let mut state = stacks(&["AB", "C"]);
+        let mut crane = HeightLimited { crane: CrateMover9001, max_height: 2 };
+        assert_eq!(perform(&mut state, &Statement::MoveAll { source: 0, dest: 1 }, &mut crane),
+            Err(CraneError::TooHigh { stack: 2, height: 3, limit: 2 }));
+        assert_eq!(state, stacks(&["AB", "C"]));
+
+        let mut crane = HeightLimited { crane: CrateMover9001, max_height: 3 };
+        perform(&mut state, &Statement::MoveAll { source: 0, dest: 1 }, &mut crane).unwrap();
+        assert_eq!(state, stacks(&["", "CAB"]));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::prelude::*;
+use super::{Crate, Stack, Instruction};
+use super::crane::{Crane, CraneError, undo_on};
+
+/**/
+// Keep a log of every instruction carried out, so the stacks can be wound back to any earlier state.
+// Whatever the crane, an instruction takes some crates off the top of the source and puts the same number on top
+// of the destination. So undoing one only needs the crates that were lifted, in their original order.
+/**/
+
+/// One instruction that has been carried out.
+#[derive(Clone, Debug)]
+pub struct Operation {
+    pub instruction: Instruction,
+    /// The crates taken off the source, bottom first.
+    pub lifted: Vec<Crate>,
+}
+
+/// Stacks that remember how they got here.
+pub struct History<C: Crane> {
+    crane: C,
+    stacks: Vec<Stack>,
+    log: Vec<Operation>,
+    /// Instructions that have been stepped back over, most recent last.
+    undone: Vec<Instruction>,
+}
+
+impl<C: Crane> History<C> {
+    pub fn new(stacks: Vec<Stack>, crane: C) -> Self {
+        History { crane, stacks, log: vec![], undone: vec![] }
+    }
+
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    pub fn log(&self) -> &[Operation] {
+        &self.log
+    }
+
+    /// Number of instructions carried out to reach the current state.
+    pub fn step(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Carry out a new instruction. Anything stepped back over can no longer be redone.
+    pub fn perform(&mut self, instr: &Instruction) -> Result<(), CraneError> {
+        self.apply(instr)?;
+        self.undone.clear();
+        Ok(())
+    }
+
+    fn apply(&mut self, instr: &Instruction) -> Result<(), CraneError> {
+        // Work out what will be lifted before the crane moves it. If the instruction is invalid, the crane says so.
+        let source = self.stacks.get(instr.source).map_or(&[][..], Vec::as_slice);
+        let lifted = source[source.len().saturating_sub(instr.count)..].to_vec();
+        self.crane.perform(&mut self.stacks, instr)?;
+        self.log.push(Operation { instruction: *instr, lifted });
+        Ok(())
+    }
+
+    /// Undo the last instruction, returning it. None if we're back at the start.
+    pub fn step_back(&mut self) -> Option<Instruction> {
+        let Operation { instruction, lifted } = self.log.pop()?;
+        let dest = &mut self.stacks[instruction.dest];
+        dest.truncate(dest.len() - instruction.count);
+        self.stacks[instruction.source].extend(lifted);
+        undo_on(&mut self.crane, &instruction);
+        self.undone.push(instruction);
+        Some(instruction)
+    }
+
+    /// Carry out the last instruction stepped back over again, returning it. None if there's nothing to redo.
+    pub fn redo(&mut self) -> Option<Instruction> {
+        let instruction = self.undone.pop()?;
+        self.apply(&instruction).expect("An instruction that worked before works again from the same state");
+        Some(instruction)
+    }
+
+    /// Step back or redo until `step` instructions have been carried out.
+    pub fn restore(&mut self, step: usize) -> Result<()> {
+        if step > self.step() + self.undone.len() {
+            return Err(anyhow!("Can't go to step {step}, only {} instructions are known", self.step() + self.undone.len()));
+        }
+        while self.step() > step {
+            self.step_back();
+        }
+        while self.step() < step {
+            self.redo();
+        }
+        Ok(())
+    }
+}
+
+/// The instructions that undo `instructions`, for either the CrateMover 9000 or 9001.
+// Moving the crates straight back undoes a move on both: the 9001 keeps their order both ways, and the 9000
+// reverses it both ways. Other cranes can need something more complicated, e.g. ones that lift a few at a time.
+pub fn inverse(instructions: &[Instruction]) -> Vec<Instruction> {
+    instructions.iter()
+        .rev()
+        .map(|instr| Instruction { count: instr.count, source: instr.dest, dest: instr.source })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::read_input;
+    use crate::day5::crane::{Alternating, CrateMover9000, CrateMover9001};
+    use crate::day5::execute::execute;
+
+    #[test]
+    fn restores_every_earlier_state() {
+        let (stacks, instructions) = read_input("input/day5.txt").unwrap();
+        let mut history = History::new(stacks.clone(), Alternating::default());
+        for (_, instr) in &instructions {
+            history.perform(instr).unwrap();
+        }
+        let end = history.stacks().to_vec();
+
+        for step in [0, 137, 1, instructions.len(), 250] {
+            history.restore(step).unwrap();
+            let mut expected = stacks.clone();
+            execute(&mut expected, &instructions[..step], &mut Alternating::default()).unwrap();
+            assert_eq!(history.stacks(), expected);
+        }
+        history.restore(instructions.len()).unwrap();
+        assert_eq!(history.stacks(), end);
+        assert!(history.restore(instructions.len() + 1).is_err());
+    }
+
+    #[test]
+    fn inverse_runs_both_cranes_backwards() {
+        let (stacks, instructions) = read_input("input/day5.txt").unwrap();
+        let instructions: Vec<Instruction> = instructions.into_iter().map(|(_, instr)| instr).collect();
+        let numbered = |list: Vec<Instruction>| list.into_iter().enumerate().collect::<Vec<_>>();
+        let inverse = numbered(inverse(&instructions));
+        let instructions = numbered(instructions);
+
+        for crane in [&mut CrateMover9000 as &mut dyn Crane, &mut CrateMover9001] {
+            let mut end = stacks.clone();
+            execute(&mut end, &instructions, crane).unwrap();
+            execute(&mut end, &inverse, crane).unwrap();
+            assert_eq!(end, stacks);
+        }
+    }
+}
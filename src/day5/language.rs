@@ -0,0 +1,285 @@
+use std::fmt;
+use std::collections::HashMap;
+use super::Instruction;
+
+/**/
+// The instruction language. Each line is one statement, and anything after a '#' is a comment:
+//   move 3 from 1 to 2        the puzzle's own instruction
+//   move all from 1 to 2      every crate on stack 1
+//   swap 3 4                  swap two whole stacks
+//   reverse 5                 turn one stack upside down
+//   rotate 1..4 by 2          shift stacks 1 to 4 (inclusive) two places right, wrapping round. Negative goes left.
+//   name 3 as dock            from then on, "dock" means stack 3
+// Stacks are numbered from 1 in the text, but from 0 once parsed like everywhere else.
+/**/
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Statement {
+    Move(Instruction),
+    MoveAll { source: usize, dest: usize },
+    Swap(usize, usize),
+    Reverse(usize),
+    /// Shift stacks `first..=last` `by` places towards `last`.
+    Rotate { first: usize, last: usize, by: isize },
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::Move(instr) => write!(f, "{instr}"),
+            Statement::MoveAll { source, dest } => write!(f, "move all from {} to {}", source + 1, dest + 1),
+            Statement::Swap(a, b) => write!(f, "swap {} {}", a + 1, b + 1),
+            Statement::Reverse(stack) => write!(f, "reverse {}", stack + 1),
+            Statement::Rotate { first, last, by } => write!(f, "rotate {}..{} by {by}", first + 1, last + 1),
+        }
+    }
+}
+
+impl Statement {
+    /// Every stack the statement can change.
+    pub fn stacks(&self) -> Vec<usize> {
+        match *self {
+            Statement::Move(Instruction { source, dest, .. }) | Statement::MoveAll { source, dest } => vec![source, dest],
+            Statement::Swap(a, b) => vec![a, b],
+            Statement::Reverse(stack) => vec![stack],
+            Statement::Rotate { first, last, .. } => (first..=last).collect(),
+        }
+    }
+}
+
+/// A statement and the line it's on, starting at 1.
+pub type NumberedStatement = (usize, Statement);
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SyntaxErrorKind {
+    UnknownCommand(String),
+    /// What was expected, and what was found instead (None at the end of the line).
+    Expected(&'static str, Option<String>),
+    InvalidNumber(String),
+    StackZero,
+    UnknownStack(String),
+    /// Stack names have to start with a letter, and can't be a keyword.
+    InvalidName(String),
+    /// A rotation whose first stack is after its last.
+    BackwardsRange,
+}
+
+/// Lines and columns start at 1.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: SyntaxErrorKind,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} column {}: ", self.line, self.column)?;
+        match &self.kind {
+            SyntaxErrorKind::UnknownCommand(word) => write!(f, "unknown command '{word}'"),
+            SyntaxErrorKind::Expected(expected, Some(found)) => write!(f, "expected {expected}, found '{found}'"),
+            SyntaxErrorKind::Expected(expected, None) => write!(f, "expected {expected}, found the end of the line"),
+            SyntaxErrorKind::InvalidNumber(word) => write!(f, "'{word}' is not a valid number"),
+            SyntaxErrorKind::StackZero => write!(f, "stacks are numbered from 1"),
+            SyntaxErrorKind::UnknownStack(name) => write!(f, "no stack has been named '{name}'"),
+            SyntaxErrorKind::InvalidName(name) => write!(f, "'{name}' can't be used as a stack name"),
+            SyntaxErrorKind::BackwardsRange => write!(f, "the first stack comes after the last"),
+        }
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+const KEYWORDS: [&str; 10] = ["move", "all", "from", "to", "swap", "reverse", "rotate", "by", "name", "as"];
+
+/// The words of one line, with the column each starts at. ".." is a word of its own.
+fn tokenise(line: &str) -> Vec<(usize, String)> {
+    let code: Vec<char> = line.chars().take_while(|&c| c != '#').collect();
+    let dots = |i: usize| code[i..].starts_with(&['.', '.']);
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < code.len() {
+        if code[i].is_whitespace() {
+            i += 1;
+        } else if dots(i) {
+            tokens.push((i + 1, "..".to_string()));
+            i += 2;
+        } else {
+            let start = i;
+            while i < code.len() && !code[i].is_whitespace() && !dots(i) {
+                i += 1;
+            }
+            tokens.push((start + 1, code[start..i].iter().collect()));
+        }
+    }
+    tokens
+}
+
+/// Reads through the words of one line.
+struct Parser<'a> {
+    line: usize,
+    tokens: Vec<(usize, String)>,
+    next: usize,
+    /// Column just past the end of the line, for errors about missing words.
+    end: usize,
+    names: &'a HashMap<String, usize>,
+}
+
+impl Parser<'_> {
+    fn error(&self, column: usize, kind: SyntaxErrorKind) -> SyntaxError {
+        SyntaxError { line: self.line, column, kind }
+    }
+
+    /// The next word and its column, or an error saying `expected` was wanted.
+    fn word(&mut self, expected: &'static str) -> Result<(usize, String), SyntaxError> {
+        let token = self.tokens.get(self.next).cloned()
+            .ok_or(self.error(self.end, SyntaxErrorKind::Expected(expected, None)))?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn keyword(&mut self, keyword: &'static str) -> Result<(), SyntaxError> {
+        match self.word(keyword)? {
+            (_, word) if word == keyword => Ok(()),
+            (column, word) => Err(self.error(column, SyntaxErrorKind::Expected(keyword, Some(word)))),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, expected: &'static str) -> Result<(usize, T), SyntaxError> {
+        let (column, word) = self.word(expected)?;
+        let number = word.parse().map_err(|_| self.error(column, SyntaxErrorKind::InvalidNumber(word)))?;
+        Ok((column, number))
+    }
+
+    /// A stack, by number or name.
+    fn stack(&mut self) -> Result<(usize, usize), SyntaxError> {
+        let (column, word) = self.word("a stack")?;
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            self.next -= 1;
+            return match self.number::<usize>("a stack")? {
+                (column, 0) => Err(self.error(column, SyntaxErrorKind::StackZero)),
+                (column, n) => Ok((column, n - 1)),
+            };
+        }
+        let stack = self.names.get(&word).copied()
+            .ok_or(self.error(column, SyntaxErrorKind::UnknownStack(word)))?;
+        Ok((column, stack))
+    }
+
+    fn finish(&mut self) -> Result<(), SyntaxError> {
+        match self.tokens.get(self.next) {
+            Some((column, word)) => Err(self.error(*column, SyntaxErrorKind::Expected("the end of the line", Some(word.clone())))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse the instructions, given each line with its line number. Blank lines, comments and names give no statements.
+pub fn parse_program<'a>(lines: impl IntoIterator<Item = (usize, &'a str)>) -> Result<Vec<NumberedStatement>, SyntaxError> {
+    let mut names = HashMap::new();
+    let mut statements = vec![];
+    for (line_no, line) in lines {
+        let tokens = tokenise(line);
+        let Some((column, command)) = tokens.first().cloned() else {continue};
+        let mut parser = Parser { line: line_no, tokens, next: 1, end: line.chars().take_while(|&c| c != '#').count() + 1, names: &names };
+
+        let statement = match command.as_str() {
+            "move" => {
+                let (_, count) = parser.word("a number of crates, or 'all'")?;
+                let count = if count == "all" {
+                    None
+                } else {
+                    parser.next -= 1;
+                    Some(parser.number::<usize>("a number of crates, or 'all'")?.1)
+                };
+                parser.keyword("from")?;
+                let (_, source) = parser.stack()?;
+                parser.keyword("to")?;
+                let (_, dest) = parser.stack()?;
+                match count {
+                    Some(count) => Statement::Move(Instruction { count, source, dest }),
+                    None => Statement::MoveAll { source, dest },
+                }
+            },
+            "swap" => Statement::Swap(parser.stack()?.1, parser.stack()?.1),
+            "reverse" => Statement::Reverse(parser.stack()?.1),
+            "rotate" => {
+                let (column, first) = parser.stack()?;
+                parser.keyword("..")?;
+                let (_, last) = parser.stack()?;
+                if first > last {
+                    return Err(parser.error(column, SyntaxErrorKind::BackwardsRange));
+                }
+                parser.keyword("by")?;
+                let (_, by) = parser.number::<isize>("a number of places")?;
+                Statement::Rotate { first, last, by }
+            },
+            "name" => {
+                let (_, stack) = parser.stack()?;
+                parser.keyword("as")?;
+                let (column, name) = parser.word("a name")?;
+                if !name.starts_with(|c: char| c.is_alphabetic()) || KEYWORDS.contains(&name.as_str()) {
+                    return Err(parser.error(column, SyntaxErrorKind::InvalidName(name)));
+                }
+                parser.finish()?;
+                names.insert(name, stack);
+                continue;
+            },
+            other => return Err(parser.error(column, SyntaxErrorKind::UnknownCommand(other.to_string()))),
+        };
+        parser.finish()?;
+        statements.push((line_no, statement));
+    }
+    Ok(statements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Vec<Statement>, SyntaxError> {
+        let program = parse_program(text.lines().enumerate().map(|(n, line)| (n + 1, line)))?;
+        Ok(program.into_iter().map(|(_, statement)| statement).collect())
+    }
+
+    #[test]
+    fn parses_every_statement() {
+        let program = "move 3 from 1 to 2\n\
+            # Just a comment\n\
+            \n\
+            move all from 2 to 1   # and a trailing one\n\
+            name 4 as dock\n\
+            swap dock 1\n\
+            reverse 5\n\
+            rotate 1..dock by -2\n\
+            rotate 2 .. 3 by 1\n";
+        assert_eq!(parse(program).unwrap(), vec![
+            Statement::Move(Instruction { count: 3, source: 0, dest: 1 }),
+            Statement::MoveAll { source: 1, dest: 0 },
+            Statement::Swap(3, 0),
+            Statement::Reverse(4),
+            Statement::Rotate { first: 0, last: 3, by: -2 },
+            Statement::Rotate { first: 1, last: 2, by: 1 },
+        ]);
+        // Printing a statement gives something that parses back to it.
+        for statement in parse(program).unwrap() {
+            assert_eq!(parse(&statement.to_string()).unwrap(), vec![statement]);
+        }
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = |text: &str| parse(text).unwrap_err();
+        assert_eq!(error("\n  jump 1"), SyntaxError { line: 2, column: 3, kind: SyntaxErrorKind::UnknownCommand("jump".to_string()) });
+        assert_eq!(error("move 3 form 1 to 2"),
+            SyntaxError { line: 1, column: 8, kind: SyntaxErrorKind::Expected("from", Some("form".to_string())) });
+        assert_eq!(error("move 3 from 1 to"), SyntaxError { line: 1, column: 17, kind: SyntaxErrorKind::Expected("a stack", None) });
+        assert_eq!(error("move x from 1 to 2"), SyntaxError { line: 1, column: 6, kind: SyntaxErrorKind::InvalidNumber("x".to_string()) });
+        assert_eq!(error("swap 0 1"), SyntaxError { line: 1, column: 6, kind: SyntaxErrorKind::StackZero });
+        assert_eq!(error("reverse dock"), SyntaxError { line: 1, column: 9, kind: SyntaxErrorKind::UnknownStack("dock".to_string()) });
+        assert_eq!(error("name 1 as move"), SyntaxError { line: 1, column: 11, kind: SyntaxErrorKind::InvalidName("move".to_string()) });
+        assert_eq!(error("rotate 4..2 by 1"), SyntaxError { line: 1, column: 8, kind: SyntaxErrorKind::BackwardsRange });
+        assert_eq!(error("reverse 1 2"),
+            SyntaxError { line: 1, column: 11, kind: SyntaxErrorKind::Expected("the end of the line", Some("2".to_string())) });
+    }
+}
@@ -0,0 +1,179 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{Crate, Stack, Instruction};
+use super::crane::Crane;
+use super::history::History;
+
+/**/
+// Search for the fewest instructions that turn one arrangement of crates into another, using IDA*.
+// Each stack matches the target up to some height. Everything above that has to be moved off, and everything the
+// target has above it has to be moved on. A single instruction only has one source and one destination, so it
+// takes at least as many instructions as there are stacks needing crates moved off, and likewise for on.
+// Searching depth first means the stacks can be changed in place and stepped back, which also keeps cranes that
+// remember earlier lifts in the right state.
+/**/
+
+#[derive(Clone, Debug)]
+pub struct Plan {
+    pub instructions: Vec<Instruction>,
+    /// Arrangements looked at.
+    pub nodes: usize,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for instr in &self.instructions {
+            writeln!(f, "{instr}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A lower bound on the instructions still needed.
+fn heuristic(stacks: &[Stack], target: &[Stack]) -> usize {
+    let mut off = 0;
+    let mut on = 0;
+    for (stack, goal) in stacks.iter().zip(target) {
+        let matching = stack.iter().zip(goal).take_while(|(a, b)| a == b).count();
+        off += (stack.len() > matching) as usize;
+        on += (goal.len() > matching) as usize;
+    }
+    off.max(on)
+}
+
+struct Search<'a, C: Crane> {
+    history: History<C>,
+    target: &'a [Stack],
+    path: Vec<Instruction>,
+    nodes: usize,
+    budget: usize,
+}
+
+enum Outcome {
+    Found,
+    /// The smallest estimated total that went over the bound, if any.
+    NotFound(Option<usize>),
+    OutOfBudget,
+}
+
+impl<C: Crane> Search<'_, C> {
+    fn search(&mut self, bound: usize) -> Outcome {
+        self.nodes += 1;
+        if self.nodes > self.budget {
+            return Outcome::OutOfBudget;
+        }
+        let estimate = self.path.len() + heuristic(self.history.stacks(), self.target);
+        if estimate > bound {
+            return Outcome::NotFound(Some(estimate));
+        }
+        if self.history.stacks() == self.target {
+            return Outcome::Found;
+        }
+
+        let mut next_bound = None;
+        let stack_count = self.target.len();
+        for source in 0..stack_count {
+            // Moving crates from a stack onto itself changes nothing, not even the crane (see `crane::perform_on`).
+            for dest in (0..stack_count).filter(|&dest| dest != source) {
+                for count in 1..=self.history.stacks()[source].len() {
+                    let instr = Instruction { count, source, dest };
+                    // The crane might refuse, e.g. if it has a height limit.
+                    if self.history.perform(&instr).is_err() {continue}
+                    self.path.push(instr);
+                    let outcome = self.search(bound);
+                    // Once found, leave the path as it is.
+                    if let Outcome::Found = outcome {
+                        return outcome;
+                    }
+                    self.path.pop();
+                    self.history.step_back();
+                    match outcome {
+                        Outcome::NotFound(Some(estimate)) => {
+                            next_bound = Some(next_bound.map_or(estimate, |b: usize| b.min(estimate)));
+                        },
+                        Outcome::NotFound(None) => (),
+                        _ => return outcome,
+                    }
+                }
+            }
+        }
+        Outcome::NotFound(next_bound)
+    }
+}
+
+/// Find the shortest list of instructions that turns `stacks` into `target` with `crane`, looking at no more than
+/// `budget` arrangements.
+pub fn plan(stacks: &[Stack], target: &[Stack], crane: impl Crane, budget: usize) -> Result<Plan> {
+    if stacks.len() != target.len() {
+        return Err(anyhow!("There are {} stacks, but the target has {}", stacks.len(), target.len()));
+    }
+    let mut crates: Vec<&Crate> = stacks.iter().flatten().collect();
+    let mut wanted: Vec<&Crate> = target.iter().flatten().collect();
+    crates.sort_unstable();
+    wanted.sort_unstable();
+    if crates != wanted {
+        return Err(anyhow!("The target doesn't have the same crates as the starting stacks"));
+    }
+
+    let mut search = Search { history: History::new(stacks.to_vec(), crane), target, path: vec![], nodes: 0, budget };
+    let mut bound = heuristic(stacks, target);
+    loop {
+        match search.search(bound) {
+            Outcome::Found => return Ok(Plan { instructions: search.path, nodes: search.nodes }),
+            Outcome::NotFound(Some(next)) => bound = next,
+            Outcome::NotFound(None) => return Err(anyhow!("The target can't be reached with this crane")),
+            Outcome::OutOfBudget => return Err(anyhow!("Gave up after looking at {budget} arrangements")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::crane::{CrateMover9000, CrateMover9001, Alternating};
+    use crate::day5::stacks;
+    use crate::day5::execute::execute;
+
+    /// Check the plan really does turn `start` into `target`, and return how long it is.
+    fn plan_length(start: &[Stack], target: &[Stack], mut crane: impl Crane) -> usize {
+        let plan = plan(start, target, &mut crane, 1_000_000).unwrap();
+        let mut state = start.to_vec();
+        let numbered: Vec<_> = plan.instructions.iter().enumerate().map(|(n, &instr)| (n + 1, instr)).collect();
+        execute(&mut state, &numbered, &mut crane).unwrap();
+        assert_eq!(state, target);
+        plan.instructions.len()
+    }
+
+    #[test]
+    fn finds_the_shortest_plan() {
+        let (start, target) = (stacks(&["ABC", "", ""]), stacks(&["", "", "ABC"]));
+        // The 9001 moves all three at once. The 9000 turns them upside down, so needs to do it twice.
+        assert_eq!(plan_length(&start, &target, CrateMover9001), 1);
+        assert_eq!(plan_length(&start, &target, CrateMover9000), 2);
+        assert_eq!(plan_length(&start, &start, CrateMover9000), 0);
+    }
+
+    #[test]
+    fn same_stack_moves_cant_reorder_crates() {
+        let (start, target) = (stacks(&["AB"]), stacks(&["BA"]));
+        let mut state = start.clone();
+        CrateMover9000.perform(&mut state, &Instruction { count: 2, source: 0, dest: 0 }).unwrap();
+        assert_eq!(state, start);
+        let error = plan(&start, &target, CrateMover9000, 1000).unwrap_err();
+        assert_eq!(error.to_string(), "The target can't be reached with this crane");
+        assert!(plan(&start, &target, Alternating::default(), 1000).is_err());
+    }
+
+    #[test]
+    fn rejects_different_crates() {
+        let error = plan(&stacks(&["AB", ""]), &stacks(&["A", "C"]), CrateMover9001, 1000).unwrap_err();
+        assert_eq!(error.to_string(), "The target doesn't have the same crates as the starting stacks");
+        assert!(plan(&stacks(&["AB", ""]), &stacks(&["AB"]), CrateMover9001, 1000).is_err());
+    }
+
+    #[test]
+    fn gives_up_when_out_of_budget() {
+        let error = plan(&stacks(&["ABCDE", "", ""]), &stacks(&["", "EACDB", ""]), CrateMover9000, 10).unwrap_err();
+        assert_eq!(error.to_string(), "Gave up after looking at 10 arrangements");
+    }
+}
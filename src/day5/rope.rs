@@ -0,0 +1,317 @@
+use crate::prelude::*;
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::rng::Rng;
+use super::{Crate, Stack, Instruction, NumberedInstruction, read_input};
+use super::crane::{CraneError, CrateMover9000, CrateMover9001};
+use super::execute::execute;
+
+/**/
+// Stacks for when there are millions of crates and instructions move thousands at a time.
+// Each stack is a treap keyed by position, so taking the top n crates off one stack and putting them on another is
+// a split and a merge, both O(log n) expected however many crates move. Reversing them for the 9000 just flips a
+// flag on the root of the moved part, which gets pushed down lazily when something later looks inside it.
+// All the nodes live in one arena, and labels are stored once each so a crate is just an index.
+/**/
+
+const NONE: usize = usize::MAX;
+
+#[derive(Clone, Debug)]
+struct Node {
+    label: usize,
+    priority: u64,
+    /// Crates in this subtree.
+    size: usize,
+    left: usize,
+    right: usize,
+    /// This subtree's order is reversed, but its children haven't been told yet.
+    reversed: bool,
+}
+
+pub struct Warehouse {
+    nodes: Vec<Node>,
+    labels: Vec<Crate>,
+    /// The root of each stack, bottom crate first.
+    roots: Vec<usize>,
+}
+
+impl Warehouse {
+    pub fn new(stacks: &[Stack]) -> Self {
+        let mut warehouse = Warehouse { nodes: vec![], labels: vec![], roots: vec![] };
+        let mut rng = Rng::new(0x5eed);
+        let mut label_index = std::collections::HashMap::new();
+        for stack in stacks {
+            let mut root = NONE;
+            for label in stack {
+                let label = *label_index.entry(label).or_insert_with(|| {
+                    warehouse.labels.push(label.clone());
+                    warehouse.labels.len() - 1
+                });
+                warehouse.nodes.push(Node { label, priority: rng.next_u64(), size: 1, left: NONE, right: NONE, reversed: false });
+                root = warehouse.merge(root, warehouse.nodes.len() - 1);
+            }
+            warehouse.roots.push(root);
+        }
+        warehouse
+    }
+
+    fn size(&self, node: usize) -> usize {
+        if node == NONE {0} else {self.nodes[node].size}
+    }
+
+    fn update(&mut self, node: usize) {
+        self.nodes[node].size = 1 + self.size(self.nodes[node].left) + self.size(self.nodes[node].right);
+    }
+
+    fn flip(&mut self, node: usize) {
+        if node != NONE {
+            self.nodes[node].reversed ^= true;
+        }
+    }
+
+    /// Apply a pending reversal to this node's children.
+    fn push_down(&mut self, node: usize) {
+        if self.nodes[node].reversed {
+            let Node { left, right, .. } = self.nodes[node];
+            self.nodes[node].left = right;
+            self.nodes[node].right = left;
+            self.nodes[node].reversed = false;
+            self.flip(left);
+            self.flip(right);
+        }
+    }
+
+    /// Split into the bottom `count` crates and the rest.
+    fn split(&mut self, node: usize, count: usize) -> (usize, usize) {
+        if node == NONE {
+            return (NONE, NONE);
+        }
+        self.push_down(node);
+        let left = self.nodes[node].left;
+        if self.size(left) >= count {
+            let (bottom, top) = self.split(left, count);
+            self.nodes[node].left = top;
+            self.update(node);
+            (bottom, node)
+        } else {
+            let (bottom, top) = self.split(self.nodes[node].right, count - self.size(left) - 1);
+            self.nodes[node].right = bottom;
+            self.update(node);
+            (node, top)
+        }
+    }
+
+    /// Put `top` on top of `bottom`.
+    fn merge(&mut self, bottom: usize, top: usize) -> usize {
+        if bottom == NONE {return top}
+        if top == NONE {return bottom}
+        if self.nodes[bottom].priority > self.nodes[top].priority {
+            self.push_down(bottom);
+            let right = self.merge(self.nodes[bottom].right, top);
+            self.nodes[bottom].right = right;
+            self.update(bottom);
+            bottom
+        } else {
+            self.push_down(top);
+            let left = self.merge(bottom, self.nodes[top].left);
+            self.nodes[top].left = left;
+            self.update(top);
+            top
+        }
+    }
+
+    pub fn height(&self, stack: usize) -> usize {
+        self.size(self.roots[stack])
+    }
+
+    /// Carry out an instruction, reversing the crates like the 9000 unless `keep_order` is set like the 9001.
+    pub fn move_crates(&mut self, instr: &Instruction, keep_order: bool) -> Result<(), CraneError> {
+        let stack_count = self.roots.len();
+        for stack in [instr.source, instr.dest] {
+            if stack >= stack_count {
+                return Err(CraneError::NoSuchStack { stack: stack + 1, stack_count });
+            }
+        }
+        let available = self.height(instr.source);
+        if instr.count > available {
+            return Err(CraneError::NotEnoughCrates { stack: instr.source + 1, requested: instr.count, available });
+        }
+        // Like the other cranes, moving crates onto the stack they came from does nothing.
+        if instr.source == instr.dest {
+            return Ok(());
+        }
+
+        let (rest, lifted) = self.split(self.roots[instr.source], available - instr.count);
+        self.roots[instr.source] = rest;
+        if !keep_order {
+            self.flip(lifted);
+        }
+        self.roots[instr.dest] = self.merge(self.roots[instr.dest], lifted);
+        Ok(())
+    }
+
+    /// The crate on top of a stack.
+    pub fn top(&self, stack: usize) -> Option<&Crate> {
+        let mut node = self.roots[stack];
+        let mut flipped = false;
+        let mut top = None;
+        while node != NONE {
+            flipped ^= self.nodes[node].reversed;
+            top = Some(node);
+            node = if flipped {self.nodes[node].left} else {self.nodes[node].right};
+        }
+        // The last node reached has nothing above it, once reversals are taken into account.
+        top.map(|node| &self.labels[self.nodes[node].label])
+    }
+
+    /// Turn back into ordinary stacks.
+    pub fn to_stacks(&self) -> Vec<Stack> {
+        self.roots.iter()
+            .map(|&root| {
+                let mut stack = Vec::with_capacity(self.size(root));
+                self.collect(root, false, &mut stack);
+                stack
+            })
+            .collect()
+    }
+
+    fn collect(&self, node: usize, flipped: bool, stack: &mut Stack) {
+        if node == NONE {return}
+        let flipped = flipped ^ self.nodes[node].reversed;
+        let Node { left, right, label, .. } = self.nodes[node];
+        let (first, second) = if flipped {(right, left)} else {(left, right)};
+        self.collect(first, flipped, stack);
+        stack.push(self.labels[label].clone());
+        self.collect(second, flipped, stack);
+    }
+
+    /// The crate on top of each stack, like the puzzle answer.
+    pub fn tops(&self) -> String {
+        (0..self.roots.len()).filter_map(|stack| self.top(stack)).map(String::as_str).collect()
+    }
+}
+
+/// Run the puzzle in any file on a `Warehouse`, with the 9001 if `keep_order` or the 9000 otherwise.
+pub fn calculate_with_rope(path: &str, keep_order: bool) -> Result<String> {
+    let (stacks, instructions) = read_input(path)?;
+    let mut warehouse = Warehouse::new(&stacks);
+    for (line_no, instr) in &instructions {
+        warehouse.move_crates(instr, keep_order).map_err(|e| anyhow!("Line {line_no} ({instr}): {e}"))?;
+    }
+    Ok(warehouse.tops())
+}
+
+/// Make up some stacks with `crates` crates in total, and `moves` valid instructions moving up to `max_move` at a time.
+/// There are fewer moves if there are no crates to move.
+pub fn generate(stack_count: usize, crates: usize, moves: usize, max_move: usize, seed: u64)
+    -> Result<(Vec<Stack>, Vec<NumberedInstruction>)> {
+    if stack_count < 2 {
+        return Err(anyhow!("Need at least 2 stacks to move crates between, not {stack_count}"));
+    }
+    if max_move == 0 {
+        return Err(anyhow!("Each move has to be allowed to move at least 1 crate"));
+    }
+    let mut rng = Rng::new(seed);
+    let mut stacks: Vec<Stack> = vec![vec![]; stack_count];
+    for _ in 0..crates {
+        let label = char::from(b'A' + rng.below(26) as u8).to_string();
+        stacks[rng.below(stack_count as u64) as usize].push(label);
+    }
+
+    // Only the heights matter for keeping the instructions valid.
+    let mut heights: Vec<usize> = stacks.iter().map(Vec::len).collect();
+    let mut instructions = vec![];
+    while instructions.len() < moves && crates > 0 {
+        let source = rng.below(stack_count as u64) as usize;
+        let dest = rng.below(stack_count as u64) as usize;
+        if heights[source] == 0 || source == dest {continue}
+        let count = 1 + rng.below(heights[source].min(max_move) as u64) as usize;
+        heights[source] -= count;
+        heights[dest] += count;
+        instructions.push((instructions.len() + 1, Instruction { count, source, dest }));
+    }
+    Ok((stacks, instructions))
+}
+
+pub struct BenchReport {
+    crates: usize,
+    moves: usize,
+    /// (name, answer, time) for each run.
+    runs: Vec<(&'static str, String, Duration)>,
+}
+
+/// Time the ordinary cranes against a `Warehouse`, for both models, on made up stacks.
+pub fn benchmark(crates: usize, moves: usize, max_move: usize) -> Result<BenchReport> {
+    let (stacks, instructions) = generate(9, crates, moves, max_move, 2022)?;
+    let mut runs = vec![];
+    for keep_order in [false, true] {
+        let start = Instant::now();
+        let mut vec_stacks = stacks.clone();
+        if keep_order {
+            execute(&mut vec_stacks, &instructions, &mut CrateMover9001)?;
+        } else {
+            execute(&mut vec_stacks, &instructions, &mut CrateMover9000)?;
+        }
+        let answer: String = vec_stacks.iter().filter_map(|stack| stack.last()).map(String::as_str).collect();
+        runs.push((if keep_order {"9001 vec"} else {"9000 vec"}, answer, start.elapsed()));
+
+        let start = Instant::now();
+        let mut warehouse = Warehouse::new(&stacks);
+        for (_, instr) in &instructions {
+            warehouse.move_crates(instr, keep_order)?;
+        }
+        runs.push((if keep_order {"9001 rope"} else {"9000 rope"}, warehouse.tops(), start.elapsed()));
+
+        // Check the whole arrangement, not just the tops.
+        if warehouse.to_stacks() != vec_stacks {
+            return Err(anyhow!("The rope and the vec ended up with different stacks!"));
+        }
+    }
+    Ok(BenchReport { crates, moves: instructions.len(), runs })
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} crates, {} moves", self.crates, self.moves)?;
+        for (name, answer, time) in &self.runs {
+            writeln!(f, "  {name:<10} {answer:>10} {:>10.2?}", time)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_vec_stacks() {
+        assert_eq!(calculate_with_rope("input/day5.txt", false).unwrap(), "NTWZZWHFV");
+        assert_eq!(calculate_with_rope("input/day5.txt", true).unwrap(), "BRZGFVBTJ");
+        // benchmark checks every stack matches at the end.
+        benchmark(2000, 2000, 300).unwrap();
+    }
+
+    #[test]
+    fn same_stack_moves_change_nothing() {
+        let stacks: Vec<Stack> = vec![["A", "B", "C"].map(String::from).to_vec()];
+        for keep_order in [false, true] {
+            let mut warehouse = Warehouse::new(&stacks);
+            warehouse.move_crates(&Instruction { count: 3, source: 0, dest: 0 }, keep_order).unwrap();
+            assert_eq!(warehouse.to_stacks(), stacks);
+            assert!(warehouse.move_crates(&Instruction { count: 4, source: 0, dest: 0 }, keep_order).is_err());
+        }
+    }
+
+    #[test]
+    fn generate_rejects_impossible_settings() {
+        assert_eq!(generate(9, 100, 10, 0, 1).unwrap_err().to_string(), "Each move has to be allowed to move at least 1 crate");
+        assert_eq!(generate(1, 100, 10, 5, 1).unwrap_err().to_string(), "Need at least 2 stacks to move crates between, not 1");
+        assert!(generate(0, 100, 10, 5, 1).is_err());
+        assert!(benchmark(100, 10, 0).is_err());
+
+        // No crates means nothing to move, but that's not an error.
+        let (stacks, instructions) = generate(3, 0, 10, 5, 1).unwrap();
+        assert_eq!((stacks.len(), instructions.len()), (3, 0));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::prelude::*;
+use std::fmt;
+use super::{Crate, Stack};
+use super::crane::Crane;
+use super::execute::{ExecutionError, crane_move, perform};
+use super::language::NumberedStatement;
+
+/**/
+// Follow individual crates through the procedure, even when several have the same label.
+// Each crate gets an id, numbering them 1, 2, 3... from the bottom of stack 1 up, then stack 2 and so on. Running the
+// crane on stacks of ids instead of labels shows exactly where each one goes, whichever crane it is.
+// Moving crates never changes the height of any crate that stays put, so a crate's position only changes when it's
+// one of the crates moved, and only those moves need recording. Swapping, reversing and rotating stacks can move
+// every crate on the stacks they touch, so those get checked crate by crate.
+/**/
+
+pub type CrateId = usize;
+
+/// Stacks and heights start at 1, with height 1 the bottom crate.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Position {
+    pub stack: usize,
+    pub height: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "stack {} height {}", self.stack, self.height)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Movement {
+    /// The statement that moved it, starting at 1.
+    pub step: usize,
+    pub statement: NumberedStatement,
+    pub from: Position,
+    pub to: Position,
+}
+
+pub struct Tracking {
+    /// Indexed by id - 1.
+    labels: Vec<Crate>,
+    start: Vec<Position>,
+    movements: Vec<Vec<Movement>>,
+}
+
+/// Run the procedure, recording every crate's moves.
+pub fn track(stacks: &[Stack], statements: &[NumberedStatement], crane: &mut dyn Crane) -> Result<Tracking> {
+    let labels: Vec<Crate> = stacks.iter().flatten().cloned().collect();
+    let mut id_stacks: Vec<Vec<CrateId>> = vec![];
+    let mut positions = vec![];
+    for (stack, crates) in stacks.iter().enumerate() {
+        let first = positions.len() + 1;
+        id_stacks.push((first..first + crates.len()).collect());
+        positions.extend((1..=crates.len()).map(|height| Position { stack: stack + 1, height }));
+    }
+    let start = positions.clone();
+
+    let mut movements = vec![vec![]; labels.len()];
+    for (n, numbered) in statements.iter().enumerate() {
+        let &(line_no, statement) = numbered;
+        let instr = crane_move(&id_stacks, &statement);
+        perform(&mut id_stacks, &statement, crane).map_err(|error| {
+            let stacks = id_stacks.iter().map(|ids| ids.iter().map(|&id| labels[id - 1].clone()).collect()).collect();
+            ExecutionError { line_no, statement, error, stacks }
+        })?;
+        // Only the crates the crane lifted can have moved, unless the statement moved whole stacks.
+        let moved: Vec<(usize, usize)> = match instr {
+            Some(instr) => {
+                let height = id_stacks[instr.dest].len();
+                (height - instr.count..height).map(|height| (instr.dest, height)).collect()
+            },
+            None => statement.stacks().into_iter()
+                .flat_map(|stack| (0..id_stacks[stack].len()).map(move |height| (stack, height)))
+                .collect(),
+        };
+        for (stack, height) in moved {
+            let id = id_stacks[stack][height];
+            let to = Position { stack: stack + 1, height: height + 1 };
+            // Crates moved onto the stack they came from, or rotated all the way round, stay where they were.
+            if positions[id - 1] == to {continue}
+            movements[id - 1].push(Movement { step: n + 1, statement: *numbered, from: positions[id - 1], to });
+            positions[id - 1] = to;
+        }
+    }
+    Ok(Tracking { labels, start, movements })
+}
+
+impl Tracking {
+    fn check(&self, id: CrateId) -> Result<()> {
+        if id == 0 || id > self.labels.len() {
+            return Err(anyhow!("There is no crate {id} (crates are numbered 1 to {})", self.labels.len()));
+        }
+        Ok(())
+    }
+
+    pub fn label(&self, id: CrateId) -> Result<&Crate> {
+        self.check(id)?;
+        Ok(&self.labels[id - 1])
+    }
+
+    /// Every crate with this label.
+    pub fn find(&self, label: &str) -> Vec<CrateId> {
+        (1..=self.labels.len()).filter(|&id| self.labels[id - 1] == label).collect()
+    }
+
+    /// Where a crate is after `step` instructions.
+    pub fn position(&self, id: CrateId, step: usize) -> Result<Position> {
+        self.check(id)?;
+        let movements = &self.movements[id - 1];
+        let moved = movements.partition_point(|m| m.step <= step);
+        Ok(if moved == 0 {self.start[id - 1]} else {movements[moved - 1].to})
+    }
+
+    /// Every move of a crate, in order.
+    pub fn history(&self, id: CrateId) -> Result<&[Movement]> {
+        self.check(id)?;
+        Ok(&self.movements[id - 1])
+    }
+
+    /// A crate's starting position and every move, for printing.
+    pub fn describe(&self, id: CrateId) -> Result<String> {
+        let mut description = format!("Crate {id} [{}] starts at {}\n", self.label(id)?, self.position(id, 0)?);
+        for movement in self.history(id)? {
+            let (line_no, statement) = movement.statement;
+            description += &format!("  step {} (line {line_no}, {statement}): {} -> {}\n", movement.step, movement.from, movement.to);
+        }
+        Ok(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::read_program;
+    use crate::day5::execute::run;
+    use crate::day5::crane::{CrateMover9000, CrateMover9001, MaxPerLift};
+    use crate::day5::language::{Statement, parse_program};
+
+    /// Check every crate's tracked position after each of `steps` against running the statements for real.
+    fn check_positions(stacks: &[Stack], statements: &[NumberedStatement], steps: &[usize]) {
+        let tracking = track(stacks, statements, &mut MaxPerLift { max: 2 }).unwrap();
+        for &step in steps {
+            let mut expected = stacks.to_vec();
+            run(&mut expected, &statements[..step], &mut MaxPerLift { max: 2 }).unwrap();
+            for id in 1..=stacks.iter().map(Vec::len).sum() {
+                let Position { stack, height } = tracking.position(id, step).unwrap();
+                assert_eq!(&expected[stack - 1][height - 1], tracking.label(id).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn positions_match_the_stacks() {
+        let (stacks, statements) = read_program("input/day5.txt").unwrap();
+        check_positions(&stacks, &statements, &[0, 1, 100, statements.len()]);
+    }
+
+    #[test]
+    fn follows_the_whole_language() {
+        let (stacks, _) = read_program("input/day5.txt").unwrap();
+        let program = ["move all from 1 to 2", "swap 2 3", "reverse 3", "rotate 1..9 by 4", "rotate 2..3 by 2",
+            "move 8 from 9 to 9", "swap 4 4"];
+        let statements = parse_program(program.into_iter().enumerate().map(|(n, line)| (n + 1, line))).unwrap();
+        check_positions(&stacks, &statements, &(0..=statements.len()).collect::<Vec<_>>());
+
+        // Statements that leave a crate where it was don't count as moving it.
+        let tracking = track(&stacks, &statements, &mut MaxPerLift { max: 2 }).unwrap();
+        let bottom_of_5 = stacks[..4].iter().map(Vec::len).sum::<usize>() + 1;
+        let steps: Vec<usize> = tracking.history(bottom_of_5).unwrap().iter().map(|m| m.step).collect();
+        assert_eq!(steps, vec![4]);
+    }
+
+    #[test]
+    fn reports_invalid_instructions() {
+        let (stacks, mut statements) = read_program("input/day5.txt").unwrap();
+        let Statement::Move(instr) = &mut statements[3].1 else {panic!("The puzzle only has moves")};
+        instr.count = 100;
+        let error = track(&stacks, &statements, &mut CrateMover9001).err().unwrap();
+        let error = error.downcast_ref::<ExecutionError>().unwrap();
+        assert_eq!(error.line_no, statements[3].0);
+        // The error shows the crates' labels, not their ids.
+        let mut expected = stacks.clone();
+        run(&mut expected, &statements[..3], &mut CrateMover9001).unwrap();
+        assert_eq!(error.stacks, expected);
+
+        let statements = parse_program([(1, "swap 1 10")]).unwrap();
+        let error = track(&stacks, &statements, &mut CrateMover9000).err().unwrap();
+        assert_eq!(error.downcast_ref::<ExecutionError>().unwrap().statement, Statement::Swap(0, 9));
+    }
+}
@@ -32,11 +32,11 @@ pub fn calculate_part1() -> Result<usize>{
 fn check_for_unique(buf: &VecDeque<char>, size: usize) -> bool {
     // Build up a string and check if each new char is already in the string.
     let mut test_string = String::from(buf[0]);
-    for n in 1..size {
-        if test_string.contains(buf[n]) { 
+    for &chr in buf.iter().take(size).skip(1) {
+        if test_string.contains(chr) { 
             return false;
         } else { 
-            test_string.push(buf[n]);
+            test_string.push(chr);
         }
     }
     true
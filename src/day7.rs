@@ -11,7 +11,7 @@ pub fn calculate_part1() -> Result<usize>{
 
     let lines = reader.lines();
 
-    for line in lines.flatten() {
+    for line in lines.map_while(Result::ok) {
         if line.is_empty() {continue;}
 
         match interpret_line(&line)? {
@@ -146,8 +146,8 @@ enum Command<'a> {
 } use Command::*;
 
 /// Take in one line of terminal input and determine what it represents
-fn interpret_line(line: &str) -> Result<LineContent> {
-    let first_char = line.chars().nth(0).unwrap(); // we checked it's non-empty already
+fn interpret_line(line: &str) -> Result<LineContent<'_>> {
+    let first_char = line.chars().next().unwrap(); // we checked it's non-empty already
     match first_char { 
         '$' => {
             let mut line = line.get(2..)
@@ -192,7 +192,7 @@ pub fn calculate_part2() -> Result<usize>{
 
     let lines = reader.lines();
 
-    for line in lines.flatten() {
+    for line in lines.map_while(Result::ok) {
         if line.is_empty() {continue;}
 
         match interpret_line(&line)? {
@@ -8,14 +8,322 @@ mod day4;
 mod day5;
 mod day6;
 mod day7; use day7::*;
+mod rng;
+#[cfg(test)]
+mod testing;
 mod prelude; use prelude::*;
 
 /**/
 // https://adventofcode.com/2022
 /**/
 
+// With no arguments, print the answer for the most recent puzzle. Otherwise run one of the extra modes below.
 fn main() -> Result<()>{
-    let result = calculate_part2()?;
-    println!("{result}");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => {
+            let result = calculate_part2()?;
+            println!("{result}");
+        },
+        // Score a strategy guide with a rules file, or one of the built-in "part1"/"part2" rule sets.
+        ["day2", "rules", rules] | ["day2", "rules", rules, _] => {
+            let guide = args.get(3).copied().unwrap_or("input/day2.txt");
+            let rules = match *rules {
+                "part1" => day2::rules::RuleSet::part1(),
+                "part2" => day2::rules::RuleSet::part2(),
+                path => day2::rules::RuleSet::from_file(path)?,
+            };
+            println!("{}", day2::rules::calculate_with_rules(guide, &rules)?);
+        },
+        // Score the guide under every interpretation of the second column.
+        ["day2", "analyse"] | ["day2", "analyse", _] => {
+            let guide = args.get(2).copied().unwrap_or("input/day2.txt");
+            print!("{}", day2::analysis::analyse(guide)?);
+        },
+        // Round-robin tournament between the built-in bots: day2 tournament [seed] [games] [rounds]
+        ["day2", "tournament", rest @ ..] if rest.len() <= 3 => {
+            let seed: u64 = rest.first().map(|s| s.parse()).transpose()?.unwrap_or(0);
+            let games: usize = rest.get(1).map(|s| s.parse()).transpose()?.unwrap_or(100);
+            let rounds: usize = rest.get(2).map(|s| s.parse()).transpose()?.unwrap_or(100);
+            let mut players = day2::tournament::default_players(seed)?;
+            print!("{}", day2::tournament::round_robin(&mut players, games, rounds, seed));
+        },
+        // Compare a guide with the best responses: day2 counter [--max-wins k] [--max-run m] [guide]
+        ["day2", "counter", rest @ ..] => {
+            let options = Options::parse(rest, &["--max-wins", "--max-run"], &[])?;
+            let constraints = day2::counter::Constraints {
+                max_wins: options.parsed("--max-wins")?,
+                max_run: options.parsed("--max-run")?,
+            };
+            print!("{}", day2::counter::compare_to_optimal(options.path("input/day2.txt"), constraints)?);
+        },
+        // Compare the scalar and batched day 3 solvers on the puzzle input repeated many times.
+        ["day3", "bench"] | ["day3", "bench", _] => {
+            let repeats: usize = args.get(2).map(|s| s.parse()).transpose()?.unwrap_or(1_000);
+            print!("{}", day3::batched::benchmark(repeats)?);
+        },
+        // Day 3 on any file: day3 <part1|part2|validate|badges|reorganise> [--lenient] [--batched] [--alphabet items] [--group-size k] [--budget n] [path]
+        // The alphabet lists every item in priority order.
+        ["day3", command @ ("part1" | "part2" | "validate" | "badges" | "reorganise"), rest @ ..] => {
+            let options = Options::parse(rest, &["--group-size", "--budget", "--alphabet"], &["--lenient", "--batched"])?;
+            let mode = if options.switch("--lenient") {day3::Mode::Lenient} else {day3::Mode::Strict};
+            let batched = options.switch("--batched");
+            let group_size = options.parsed("--group-size")?.unwrap_or(3);
+            let budget = options.parsed("--budget")?.unwrap_or(10_000_000);
+            let alphabet = match options.value("--alphabet") {
+                Some(items) => day3::items::Alphabet::from_items(items),
+                None => day3::items::Alphabet::letters(),
+            };
+            let path = options.path("input/day3.txt");
+            match *command {
+                "part1" if batched => println!("{}", day3::batched::calculate_part1_batched(path, &alphabet, mode)?),
+                "part2" if batched => println!("{}", day3::batched::calculate_part2_batched(path, &alphabet, mode)?),
+                "part1" => println!("{}", day3::calculate_part1_with(path, &alphabet, mode)?),
+                "part2" => println!("{}", day3::calculate_part2_with(path, &alphabet, mode)?),
+                "validate" => print!("{}", day3::validate::validate(path, &alphabet)?),
+                "reorganise" => print!("{}", day3::reorganise::plan_file(path, &alphabet)?),
+                _ => print!("{}", day3::badges::discover_groups_in_file(path, group_size, &alphabet, budget)?),
+            }
+        },
+        // Day 4 on any file: day4 <part1|part2|coverage|roster|reassign> [--normalise] [--pairs] [--elves] [--output file] [--type t] [path]
+        // --normalise swaps reversed ranges instead of rejecting them. --type sets what sections are parsed as:
+        // any integer type (u8..u128, i8..i128), date (2024-01-31) or datetime (2024-01-31T09:30). Defaults to u32. --pairs lists every overlapping pair for roster.
+        // reassign writes the new assignments to --output (or stdout), minimising sections dropped, or elves changed with --elves.
+        ["day4", command @ ("part1" | "part2" | "coverage" | "roster" | "reassign"), rest @ ..] => {
+            let options = Options::parse(rest, &["--output", "--type"], &["--normalise", "--pairs", "--elves"])?;
+            let mode = if options.switch("--normalise") {day4::Mode::Normalise} else {day4::Mode::Strict};
+            let path = options.path("input/day4.txt");
+
+            fn run<T: day4::intervals::Discrete + std::fmt::Display>(
+                command: &str, path: &str, mode: day4::Mode, parse: fn(&str) -> Option<T>, options: &Options,
+            ) -> Result<()> {
+                match command {
+                    "part1" => println!("{}", day4::calculate_part1_with(path, mode, parse)?),
+                    "part2" => println!("{}", day4::calculate_part2_with(path, mode, parse)?),
+                    "coverage" => {
+                        let coverage = day4::coverage(path, mode, parse)?;
+                        let format = |set: &day4::intervals::IntervalSet<T>| set.ranges().iter()
+                            .map(|r| format!("{}-{}", r.start(), r.end()))
+                            .collect::<Vec<String>>()
+                            .join(",");
+                        println!("Covered: {} sections ({})", coverage.covered.len(), format(&coverage.covered));
+                        println!("Covered by nobody: {} sections ({})", coverage.uncovered.len(), format(&coverage.uncovered));
+                        println!("Covered twice or more: {} sections ({})", coverage.covered_twice.len(), format(&coverage.covered_twice));
+                    },
+                    "reassign" => {
+                        let objective = if options.switch("--elves") {
+                            day4::reassign::Objective::FewestElvesReassigned
+                        } else {
+                            day4::reassign::Objective::FewestSectionsDropped
+                        };
+                        let report = day4::reassign::reassign_file(path, mode, parse, objective)?;
+                        eprint!("{report}");
+                        match options.value("--output") {
+                            Some(output) => std::fs::write(output, report.to_input()?)?,
+                            None => print!("{}", report.to_input()?),
+                        }
+                    },
+                    _ => {
+                        let report = day4::roster::analyse_roster(path, mode, parse)?;
+                        if options.switch("--pairs") {
+                            print!("{}", report.display_pairs());
+                        } else {
+                            print!("{report}");
+                        }
+                    },
+                }
+                Ok(())
+            }
+            use day4::parse_number;
+            match options.value("--type").unwrap_or("u32") {
+                "u8" => run(command, path, mode, parse_number::<u8>, &options)?,
+                "u16" => run(command, path, mode, parse_number::<u16>, &options)?,
+                "u32" => run(command, path, mode, parse_number::<u32>, &options)?,
+                "u64" => run(command, path, mode, parse_number::<u64>, &options)?,
+                "u128" => run(command, path, mode, parse_number::<u128>, &options)?,
+                "i8" => run(command, path, mode, parse_number::<i8>, &options)?,
+                "i16" => run(command, path, mode, parse_number::<i16>, &options)?,
+                "i32" => run(command, path, mode, parse_number::<i32>, &options)?,
+                "i64" => run(command, path, mode, parse_number::<i64>, &options)?,
+                "i128" => run(command, path, mode, parse_number::<i128>, &options)?,
+                "date" => run(command, path, mode, day4::parse_date, &options)?,
+                "datetime" => run(command, path, mode, day4::parse_datetime, &options)?,
+                other => return Err(anyhow!("Unknown section type '{other}'")),
+            }
+        },
+        // Day 5 with any crane: day5 crane <name> [path]. See day5::crane::crane_by_name for the names.
+        // "rope:9000" and "rope:9001" run the 9000 and 9001 on rope-based stacks instead, for huge inputs.
+        ["day5", "crane", name] | ["day5", "crane", name, _] => {
+            let path = args.get(3).copied().unwrap_or("input/day5.txt");
+            match *name {
+                "rope:9000" => println!("{}", day5::rope::calculate_with_rope(path, false)?),
+                "rope:9001" => println!("{}", day5::rope::calculate_with_rope(path, true)?),
+                name => println!("{}", day5::calculate_with(path, &mut day5::crane::crane_by_name(name)?)?),
+            }
+        },
+        // Run day 5 instructions in the full language (see day5::language), then print the stacks and the crate on top
+        // of each: day5 run [--crane name] [path]
+        ["day5", "run", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane"], &[])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let path = options.path("input/day5.txt");
+            let (mut stacks, statements) = day5::read_program(path)?;
+            day5::execute::run(&mut stacks, &statements, &mut day5::crane::crane_by_name(crane)?)?;
+            print!("{}", day5::render(&stacks));
+            println!("{}", stacks.iter().filter_map(|stack| stack.last()).map(String::as_str).collect::<String>());
+        },
+        // Check every day 5 instruction can be carried out, without running them: day5 check [--crane name] [path]
+        ["day5", "check", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane"], &[])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let path = options.path("input/day5.txt");
+            let mut crane = day5::crane::crane_by_name(crane)?;
+            let (stacks, statements) = day5::read_program(path)?;
+            let result = day5::execute::dry_run_program(&stacks, &statements, &mut crane)?;
+            println!("All {} instructions can be carried out. The stacks would end up as:", statements.len());
+            print!("{}", day5::render(&result));
+        },
+        // Draw the day 5 stacks after some of the instructions, in the puzzle's format: day5 render [--crane name] [--step k] [path]
+        // Without --step, draws the final state.
+        ["day5", "render", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane", "--step"], &[])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let step: Option<usize> = options.parsed("--step")?;
+            let path = options.path("input/day5.txt");
+            let mut crane = day5::crane::crane_by_name(crane)?;
+            let (mut stacks, statements) = day5::read_program(path)?;
+            let step = step.unwrap_or(statements.len()).min(statements.len());
+            day5::execute::run(&mut stacks, &statements[..step], &mut crane)?;
+            print!("{}", day5::render(&stacks));
+        },
+        // Write a puzzle that runs day 5 backwards, from the final stacks to the starting ones: day5 reverse [--crane 9000|9001] [path]
+        // Only the puzzle's own moves can be reversed, not the rest of the language.
+        ["day5", "reverse", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane"], &[])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let path = options.path("input/day5.txt");
+            if !["9000", "9001"].contains(&crane) {
+                return Err(anyhow!("Can only reverse the 9000 or 9001, not '{crane}'"));
+            }
+            let (stacks, instructions) = day5::read_input(path)?;
+            let end = day5::execute::dry_run(&stacks, &instructions, &mut day5::crane::crane_by_name(crane)?)?;
+            let instructions: Vec<day5::Instruction> = instructions.into_iter().map(|(_, instr)| instr).collect();
+            println!("{}", day5::render(&end));
+            for instr in day5::history::inverse(&instructions) {
+                println!("{instr}");
+            }
+        },
+        // Compare the ordinary day 5 stacks with the rope-based ones: day5 bench [crates=1000000] [moves=10000] [max-move=100000]
+        ["day5", "bench", rest @ ..] if rest.len() <= 3 => {
+            let number = |n: usize, default: usize| rest.get(n).map_or(Ok(default), |value| value.parse());
+            let max_move = number(2, 100_000)?;
+            if max_move == 0 {
+                return Err(anyhow!("The largest move has to be at least 1 crate"));
+            }
+            print!("{}", day5::rope::benchmark(number(0, 1_000_000)?, number(1, 10_000)?, max_move)?);
+        },
+        // Follow day 5 crates: day5 track (--crate id | --label X) [--step k] [--crane name] [path]
+        // Crates are numbered from 1, from the bottom of stack 1 up, then stack 2 and so on. --label tracks every crate
+        // with that label. --step only says where they are after k instructions, instead of every move.
+        ["day5", "track", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane", "--crate", "--label", "--step"], &[])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let crate_id = options.parsed("--crate")?;
+            let label = options.value("--label");
+            let step = options.parsed("--step")?;
+            let path = options.path("input/day5.txt");
+            let (stacks, statements) = day5::read_program(path)?;
+            let tracking = day5::track::track(&stacks, &statements, &mut day5::crane::crane_by_name(crane)?)?;
+            let ids = match (crate_id, label) {
+                (Some(id), None) => vec![id],
+                (None, Some(label)) => tracking.find(label),
+                _ => return Err(anyhow!("Expected one of --crate or --label")),
+            };
+            for id in ids {
+                match step {
+                    Some(step) => println!("Crate {id} [{}] after step {step}: {}", tracking.label(id)?, tracking.position(id, step)?),
+                    None => print!("{}", tracking.describe(id)?),
+                }
+            }
+        },
+        // Find the fewest day 5 instructions from one drawing of the stacks to another:
+        // day5 plan <start drawing> <target drawing> [--crane name] [--budget n]
+        ["day5", "plan", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane", "--budget"], &[])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let budget = options.parsed("--budget")?.unwrap_or(1_000_000);
+            let [start, target] = options.positional[..] else {
+                return Err(anyhow!("Expected a start and a target drawing"));
+            };
+            let start = day5::parse_drawing(&std::fs::read_to_string(start)?)?;
+            let target = day5::parse_drawing(&std::fs::read_to_string(target)?)?;
+            let plan = day5::planner::plan(&start, &target, day5::crane::crane_by_name(crane)?, budget)?;
+            eprintln!("{} instructions, {} arrangements looked at", plan.instructions.len(), plan.nodes);
+            print!("{plan}");
+        },
+        // Watch day 5 play out: day5 animate [--crane name] [--delay ms] [--step] [--frames file] [path]
+        // --step starts paused, and --frames writes every frame to a file instead of playing them.
+        ["day5", "animate", rest @ ..] => {
+            let options = Options::parse(rest, &["--crane", "--delay", "--frames"], &["--step"])?;
+            let crane = options.value("--crane").unwrap_or("9000");
+            let delay = options.parsed("--delay")?.unwrap_or(200);
+            let start_paused = options.switch("--step");
+            let frames_file = options.value("--frames");
+            let path = options.path("input/day5.txt");
+            let mut crane = day5::crane::crane_by_name(crane)?;
+            match frames_file {
+                Some(output) => day5::animate::dump_frames(path, &mut crane, output)?,
+                None => day5::animate::play(path, &mut crane, std::time::Duration::from_millis(delay), start_paused)?,
+            }
+        },
+        _ => return Err(anyhow!("Unrecognised arguments: {}", args.join(" "))),
+    }
     Ok(())
 }
+
+/// The flags given to one of the modes above. Flags listed as taking a value use the argument after them, flags
+/// listed as switches stand alone, and everything else is positional (usually a path). Later flags override earlier ones.
+struct Options<'a> {
+    values: std::collections::HashMap<&'a str, &'a str>,
+    switches: Vec<&'a str>,
+    positional: Vec<&'a str>,
+}
+
+impl<'a> Options<'a> {
+    fn parse(args: &[&'a str], with_values: &[&str], switches: &[&str]) -> Result<Options<'a>> {
+        let mut options = Options { values: Default::default(), switches: vec![], positional: vec![] };
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            if with_values.contains(&arg) {
+                options.values.insert(arg, args.next().ok_or(anyhow!("Missing value for {arg}"))?);
+            } else if switches.contains(&arg) {
+                options.switches.push(arg);
+            } else {
+                options.positional.push(arg);
+            }
+        }
+        Ok(options)
+    }
+
+    fn value(&self, flag: &str) -> Option<&'a str> {
+        self.values.get(flag).copied()
+    }
+
+    /// The value of a flag parsed as a number (or anything else), if it was given.
+    fn parsed<T: std::str::FromStr>(&self, flag: &str) -> Result<Option<T>> where T::Err: std::fmt::Display {
+        self.value(flag)
+            .map(|value| value.parse().map_err(|e| anyhow!("Invalid value '{value}' for {flag}: {e}")))
+            .transpose()
+    }
+
+    fn switch(&self, flag: &str) -> bool {
+        self.switches.contains(&flag)
+    }
+
+    /// The last positional argument, or `default` if there weren't any.
+    fn path(&self, default: &'a str) -> &'a str {
+        self.positional.last().copied().unwrap_or(default)
+    }
+}
@@ -0,0 +1,25 @@
+/**/
+// Randomness for anything that needs to be repeatable, like tournaments and generated puzzles.
+/**/
+
+/// Small deterministic PRNG (SplitMix64). Any seed, including 0, is fine.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// A value in 0..n. The modulo bias is negligible unless n is close to 2^64.
+    /// Panics if n is 0, as there's nothing to pick.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Rng::below needs a non-empty range");
+        self.next_u64() % n
+    }
+}
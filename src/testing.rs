@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+/**/
+// Helpers shared by the tests of every day.
+/**/
+
+/// A file in the temporary directory, which is removed when dropped, even if the test fails first.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Tests run at the same time, so each needs its own `name`.
+    pub fn new(name: &str, contents: &str) -> TempFile {
+        let file = TempFile(std::env::temp_dir().join(format!("{name}_{}.txt", std::process::id())));
+        file.write(contents);
+        file
+    }
+
+    pub fn write(&self, contents: &str) {
+        std::fs::write(&self.0, contents).unwrap();
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // Nothing to do if it's already gone.
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Write `contents` to a temporary file for the duration of `test`.
+pub fn with_input(name: &str, contents: &str, test: impl FnOnce(&str)) {
+    let file = TempFile::new(name, contents);
+    test(file.path());
+}